@@ -0,0 +1,8 @@
+ALTER TABLE feeds ADD COLUMN interval INTEGER;
+
+CREATE TABLE IF NOT EXISTS settings (
+  key TEXT PRIMARY KEY,
+  value NOT NULL
+);
+
+INSERT OR IGNORE INTO settings VALUES('refresh_interval', 1800);
@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO settings VALUES('fetch_concurrency', 8);
+INSERT OR IGNORE INTO settings VALUES('fetch_host_concurrency', 2);
@@ -0,0 +1,3 @@
+ALTER TABLE feeds ADD COLUMN last_fetched INTEGER;
+ALTER TABLE feeds ADD COLUMN last_error TEXT;
+ALTER TABLE feeds ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
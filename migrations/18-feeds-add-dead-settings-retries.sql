@@ -0,0 +1,4 @@
+ALTER TABLE feeds ADD COLUMN dead INTEGER NOT NULL DEFAULT 0;
+
+INSERT OR IGNORE INTO settings VALUES('fetch_retries', 3);
+INSERT OR IGNORE INTO settings VALUES('dead_after_failures', 10);
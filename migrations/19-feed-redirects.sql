@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS feed_redirects (
+  id INTEGER PRIMARY KEY,
+  feed_id INTEGER NOT NULL REFERENCES feeds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+  from_url TEXT NOT NULL,
+  to_url TEXT NOT NULL,
+  status INTEGER NOT NULL,
+  created_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  )
+);
+
+CREATE INDEX IF NOT EXISTS index_feed_redirects_from_url ON feed_redirects (from_url);
@@ -0,0 +1,4 @@
+ALTER TABLE articles ADD COLUMN read INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE articles ADD COLUMN read_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS index_articles_feed_id_read ON articles (feed_id, read);
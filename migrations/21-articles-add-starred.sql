@@ -0,0 +1,3 @@
+ALTER TABLE articles ADD COLUMN starred INTEGER;
+
+CREATE INDEX IF NOT EXISTS index_articles_starred ON articles (starred) WHERE starred IS NOT NULL;
@@ -0,0 +1,91 @@
+CREATE VIRTUAL TABLE IF NOT EXISTS articles_fts USING fts5 (
+  title,
+  content,
+  authors,
+  tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO articles_fts (rowid, title, content, authors)
+SELECT
+  t.id,
+  ifnull(t.title, ''),
+  strip_html(t.content),
+  ifnull((
+    SELECT
+      group_concat(a.name, ' ')
+    FROM
+      authors AS a
+    JOIN
+      article_authors AS aa
+    ON
+      aa.a = a.id
+    WHERE
+      aa.t = t.id
+  ), '')
+FROM
+  articles AS t;
+
+CREATE TRIGGER IF NOT EXISTS articles_fts_insert AFTER INSERT ON articles
+BEGIN
+  INSERT INTO articles_fts (rowid, title, content, authors)
+  VALUES (new.id, ifnull(new.title, ''), strip_html(new.content), '');
+END;
+
+CREATE TRIGGER IF NOT EXISTS articles_fts_update AFTER UPDATE OF title, content ON articles
+WHEN old.title IS NOT new.title OR old.content IS NOT new.content
+BEGIN
+  UPDATE
+    articles_fts
+  SET
+    title = ifnull(new.title, ''),
+    content = strip_html(new.content)
+  WHERE
+    rowid = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS articles_fts_delete AFTER DELETE ON articles
+BEGIN
+  DELETE FROM articles_fts WHERE rowid = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS article_authors_fts_insert AFTER INSERT ON article_authors
+BEGIN
+  UPDATE
+    articles_fts
+  SET
+    authors = ifnull((
+      SELECT
+        group_concat(a.name, ' ')
+      FROM
+        authors AS a
+      JOIN
+        article_authors AS aa
+      ON
+        aa.a = a.id
+      WHERE
+        aa.t = new.t
+    ), '')
+  WHERE
+    rowid = new.t;
+END;
+
+CREATE TRIGGER IF NOT EXISTS article_authors_fts_delete AFTER DELETE ON article_authors
+BEGIN
+  UPDATE
+    articles_fts
+  SET
+    authors = ifnull((
+      SELECT
+        group_concat(a.name, ' ')
+      FROM
+        authors AS a
+      JOIN
+        article_authors AS aa
+      ON
+        aa.a = a.id
+      WHERE
+        aa.t = old.t
+    ), '')
+  WHERE
+    rowid = old.t;
+END;
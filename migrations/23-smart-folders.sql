@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS smart_folders (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  filter TEXT NOT NULL DEFAULT '{}',
+  created_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  ),
+  updated_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  )
+);
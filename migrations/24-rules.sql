@@ -0,0 +1,40 @@
+CREATE TABLE IF NOT EXISTS rules (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  enabled INTEGER NOT NULL DEFAULT 1,
+  conditions TEXT NOT NULL DEFAULT '[]',
+  actions TEXT NOT NULL DEFAULT '[]',
+  created_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  ),
+  updated_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  )
+);
+
+CREATE TABLE IF NOT EXISTS tags (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  created_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  )
+);
+
+CREATE TABLE IF NOT EXISTS article_tags (
+  t INTEGER NOT NULL REFERENCES articles(id) ON DELETE CASCADE ON UPDATE CASCADE,
+  g INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE ON UPDATE CASCADE,
+  PRIMARY KEY(t, g)
+);
+
+CREATE INDEX IF NOT EXISTS index_article_tags_g ON article_tags (g);
+
+-- categories of the entry as a JSON array
+ALTER TABLE articles ADD COLUMN categories TEXT;
+-- moved to the view of a folder by a rule
+ALTER TABLE articles ADD COLUMN folder_id INTEGER REFERENCES folders(id) ON DELETE SET NULL;
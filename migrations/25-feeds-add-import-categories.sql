@@ -0,0 +1,2 @@
+-- categories of new articles become tags
+ALTER TABLE feeds ADD COLUMN import_categories INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,15 @@
+-- articles kept by a feed, NULL follows the global settings and 0 keeps all
+ALTER TABLE feeds ADD COLUMN keep_days INTEGER;
+ALTER TABLE feeds ADD COLUMN keep_articles INTEGER;
+
+-- pruned articles are not stored again by later fetches
+CREATE TABLE IF NOT EXISTS pruned_articles (
+  feed_id INTEGER NOT NULL REFERENCES feeds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+  url TEXT NOT NULL,
+  pruned_at INTEGER NOT NULL DEFAULT (
+    CAST(
+      ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+    )
+  ),
+  PRIMARY KEY(feed_id, url)
+);
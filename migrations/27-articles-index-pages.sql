@@ -0,0 +1,4 @@
+-- pages of articles start after the last article of the previous page, the
+-- latest first
+CREATE INDEX IF NOT EXISTS index_articles_at ON articles (max(created, updated), id);
+CREATE INDEX IF NOT EXISTS index_articles_feed_id_at ON articles (feed_id, max(created, updated), id);
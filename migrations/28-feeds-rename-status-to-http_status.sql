@@ -0,0 +1 @@
+ALTER TABLE feeds RENAME COLUMN status TO http_status;
@@ -0,0 +1 @@
+ALTER TABLE feeds ADD COLUMN retry_at INTEGER;
@@ -1,4 +1,5 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::PathBuf,
     sync::{Arc, RwLock},
 };
@@ -6,10 +7,23 @@ use std::{
 use anyhow::Result;
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::OptionalExtension;
+use rusqlite::{
+    functions::FunctionFlags,
+    types::{FromSql, ToSql},
+    Connection, OptionalExtension,
+};
 use rusqlite_migration::{Migrations, M};
 
-use crate::models::{Article, Entry, Feed, FeedType, Folder, Person};
+use crate::{
+    models::{
+        Article, ArticleSummary, Cursor, Entry, Feed, FeedSettings, FeedType, Folder, Person,
+        Scope, SmartFilter, SmartFolder, Tag, Timeline,
+    },
+    opml::Opml,
+    rules::{self, Candidate, Outcome, Rule},
+    search::{self, Filters, Hit},
+    utils,
+};
 
 // https://cj.rs/blog/sqlite-pragma-cheatsheet-for-performance-and-consistency/
 // https://developer.apple.com/documentation/xcode/reducing-disk-writes
@@ -29,6 +43,16 @@ pub fn init(
                 PRAGMA busy_timeout = 5000;
             "#,
             )?;
+            // the full-text index keeps the text of articles
+            c.create_scalar_function(
+                "strip_html",
+                1,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                |ctx| {
+                    let html = ctx.get::<Option<String>>(0)?;
+                    Ok(utils::strip_html(html.as_deref().unwrap_or_default()))
+                },
+            )?;
             Ok(())
         }),
     )?;
@@ -67,6 +91,36 @@ fn migrate(
         M::up(include_str!(
             "../migrations/13-feeds-add-unique-index-feed_id-url.sql"
         )),
+        M::up(include_str!(
+            "../migrations/14-feeds-add-etag-last-modified-status.sql"
+        )),
+        M::up(include_str!(
+            "../migrations/15-feeds-add-interval-settings.sql"
+        )),
+        M::up(include_str!(
+            "../migrations/16-settings-fetch-concurrency.sql"
+        )),
+        M::up(include_str!(
+            "../migrations/17-feeds-add-last-fetched-last-error-failures.sql"
+        )),
+        M::up(include_str!(
+            "../migrations/18-feeds-add-dead-settings-retries.sql"
+        )),
+        M::up(include_str!("../migrations/19-feed-redirects.sql")),
+        M::up(include_str!("../migrations/20-articles-add-read.sql")),
+        M::up(include_str!("../migrations/21-articles-add-starred.sql")),
+        M::up(include_str!("../migrations/22-articles-fts.sql")),
+        M::up(include_str!("../migrations/23-smart-folders.sql")),
+        M::up(include_str!("../migrations/24-rules.sql")),
+        M::up(include_str!(
+            "../migrations/25-feeds-add-import-categories.sql"
+        )),
+        M::up(include_str!("../migrations/26-retention.sql")),
+        M::up(include_str!("../migrations/27-articles-index-pages.sql")),
+        M::up(include_str!(
+            "../migrations/28-feeds-rename-status-to-http_status.sql"
+        )),
+        M::up(include_str!("../migrations/29-feeds-add-retry-at.sql")),
     ]);
 
     migrations.to_latest(conn)?;
@@ -80,6 +134,67 @@ fn migrate(
     Ok(())
 }
 
+/// Default refresh interval of feeds, in seconds.
+pub const REFRESH_INTERVAL: &str = "refresh_interval";
+/// Max number of concurrent fetches.
+pub const FETCH_CONCURRENCY: &str = "fetch_concurrency";
+/// Max number of concurrent fetches to the same host.
+pub const FETCH_HOST_CONCURRENCY: &str = "fetch_host_concurrency";
+/// Retries of a fetch on network errors, `429` and `5xx`.
+pub const FETCH_RETRIES: &str = "fetch_retries";
+/// Consecutive failures before a feed is marked as dead.
+pub const DEAD_AFTER_FAILURES: &str = "dead_after_failures";
+/// Days articles are kept, `0` keeps them all.
+pub const KEEP_DAYS: &str = "keep_days";
+/// Latest articles kept by a feed, `0` keeps them all.
+pub const KEEP_ARTICLES: &str = "keep_articles";
+
+pub fn get_setting<T: FromSql>(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    key: &str,
+) -> Result<Option<T>> {
+    let value = conn
+        .query_row(
+            r#"
+            SELECT
+                value
+            FROM
+                settings
+            WHERE
+                key = ?1
+            "#,
+            [key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value)
+}
+
+pub fn set_setting<T: ToSql>(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    key: &str,
+    value: T,
+) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        INSERT INTO settings (
+            key,
+            value
+        )
+        VALUES (
+            ?1,
+            ?2
+        )
+        ON CONFLICT(key) DO
+        UPDATE
+        SET
+            value = EXCLUDED.value
+        "#,
+        rusqlite::params![key, value],
+    )?;
+    Ok(changed)
+}
+
 pub fn fetch_folders(conn: &mut PooledConnection<SqliteConnectionManager>) -> Result<Vec<Folder>> {
     let folders = conn
         .prepare_cached(
@@ -104,7 +219,41 @@ pub fn fetch_folders(conn: &mut PooledConnection<SqliteConnectionManager>) -> Re
                     f.name,
                     f.url,
                     f.site,
+                    f.type,
                     f.last_seen,
+                    f.etag,
+                    f.last_modified,
+                    f.http_status,
+                    f.interval,
+                    f.last_fetched,
+                    f.last_error,
+                    f.failures,
+                    f.dead,
+                    f.import_categories,
+                    f.keep_days,
+                    f.keep_articles,
+                    f.retry_at,
+                    (
+                        SELECT
+                            from_url
+                        FROM
+                            feed_redirects
+                        WHERE
+                            feed_id = f.id
+                        ORDER BY
+                            id DESC
+                        LIMIT 1
+                    ) AS moved_from,
+                    (
+                        SELECT
+                            count(*)
+                        FROM
+                            articles
+                        WHERE
+                            feed_id = f.id
+                        AND
+                            read = 0
+                    ) AS unread,
                     df.d
                 FROM
                     feeds AS f
@@ -129,8 +278,38 @@ pub fn fetch_folders(conn: &mut PooledConnection<SqliteConnectionManager>) -> Re
                             f.url,
                             'site',
                             f.site,
+                            'kind',
+                            f.type,
                             'last_seen',
                             f.last_seen,
+                            'etag',
+                            f.etag,
+                            'last_modified',
+                            f.last_modified,
+                            'http_status',
+                            f.http_status,
+                            'interval',
+                            f.interval,
+                            'last_fetched',
+                            f.last_fetched,
+                            'last_error',
+                            f.last_error,
+                            'failures',
+                            f.failures,
+                            'dead',
+                            json(iif(f.dead, 'true', 'false')),
+                            'import_categories',
+                            json(iif(f.import_categories, 'true', 'false')),
+                            'keep_days',
+                            f.keep_days,
+                            'keep_articles',
+                            f.keep_articles,
+                            'retry_at',
+                            f.retry_at,
+                            'moved_from',
+                            f.moved_from,
+                            'unread',
+                            f.unread,
                             'folder_id',
                             d.id
                         )
@@ -162,8 +341,12 @@ pub fn fetch_folders(conn: &mut PooledConnection<SqliteConnectionManager>) -> Re
 
 pub fn create_folder(
     conn: &mut PooledConnection<SqliteConnectionManager>,
-    Folder { name, .. }: &Folder,
+    folder: &Folder,
 ) -> Result<u64> {
+    insert_folder(conn, folder)
+}
+
+fn insert_folder(conn: &Connection, Folder { name, .. }: &Folder) -> Result<u64> {
     let id = conn.query_row(
         r#"
         INSERT INTO folders (
@@ -230,6 +413,16 @@ pub fn delete_folder(
 
 pub fn create_feed(
     conn: &mut PooledConnection<SqliteConnectionManager>,
+    feed: &Feed,
+) -> Result<u64> {
+    let t = conn.transaction()?;
+    let id = insert_feed(&t, feed)?;
+    t.commit()?;
+    Ok(id)
+}
+
+fn insert_feed(
+    conn: &Connection,
     Feed {
         url,
         name,
@@ -237,8 +430,7 @@ pub fn create_feed(
         ..
     }: &Feed,
 ) -> Result<u64> {
-    let t = conn.transaction()?;
-    let id = t.query_row(
+    let id = conn.query_row(
         r#"
         INSERT INTO feeds (
             url,
@@ -254,7 +446,7 @@ pub fn create_feed(
         [url, name],
         |row| row.get(0),
     )?;
-    t.execute(
+    conn.execute(
         r#"
         INSERT INTO folder_feeds (
             f,
@@ -267,10 +459,94 @@ pub fn create_feed(
         "#,
         [id, *folder_id],
     )?;
-    t.commit()?;
     Ok(id)
 }
 
+/// Creates the folders and feeds of an OPML document in one transaction, a
+/// feed is skipped when its url is subscribed or was moved. Folders without a
+/// name are the default folder.
+///
+/// Returns the touched folders with their new feeds, and the number of skipped
+/// feeds.
+pub fn import_opml(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    opml: &Opml,
+) -> Result<(Vec<Folder>, usize)> {
+    let t = conn.transaction()?;
+    let mut urls = t
+        .prepare(
+            r#"
+            SELECT
+                url
+            FROM
+                feeds
+            UNION
+            SELECT
+                from_url
+            FROM
+                feed_redirects
+            "#,
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<HashSet<_>, _>>()?;
+    let names = t
+        .prepare(
+            r#"
+            SELECT
+                name,
+                id
+            FROM
+                folders
+            "#,
+        )?
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<_, u64>, _>>()?;
+
+    let mut folders = Vec::<Folder>::new();
+    let mut skipped = 0;
+    for folder in &opml.folders {
+        let mut feeds = folder
+            .feeds
+            .iter()
+            .flatten()
+            .filter(|feed| {
+                let new = urls.insert(feed.url.clone());
+                if !new {
+                    skipped += 1;
+                }
+                new
+            })
+            .cloned()
+            .peekable();
+        if feeds.peek().is_none() {
+            continue;
+        }
+
+        let id = match names.get(&folder.name) {
+            Some(id) => *id,
+            None if folder.name.is_empty() => 1,
+            None => insert_folder(&t, folder)?,
+        };
+        let feeds = feeds
+            .map(|mut feed| {
+                feed.folder_id = id;
+                feed.id = insert_feed(&t, &feed)?;
+                Ok(feed)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match folders.iter_mut().find(|f| f.id == id) {
+            Some(f) => f.feeds.get_or_insert_with(Vec::new).extend(feeds),
+            None => folders.push(Folder {
+                id,
+                name: folder.name.clone(),
+                feeds: Some(feeds),
+            }),
+        }
+    }
+    t.commit()?;
+    Ok((folders, skipped))
+}
+
 pub fn delete_feed(
     conn: &mut PooledConnection<SqliteConnectionManager>,
     Feed { id, .. }: &Feed,
@@ -291,15 +567,30 @@ pub fn delete_feed(
 
 pub fn update_feed(
     conn: &mut PooledConnection<SqliteConnectionManager>,
-    Feed {
+    FeedSettings {
         id,
         name,
         url,
         folder_id,
-        ..
-    }: &Feed,
+        interval,
+        import_categories,
+        keep_days,
+        keep_articles,
+    }: &FeedSettings,
 ) -> Result<(u64, usize)> {
     let t = conn.transaction()?;
+    let imported: bool = t.query_row(
+        r#"
+        SELECT
+            import_categories
+        FROM
+            feeds
+        WHERE
+            id = ?1
+        "#,
+        [id],
+        |row| row.get(0),
+    )?;
     let prev_folder_id = t
         .query_row(
             r#"
@@ -336,40 +627,219 @@ pub fn update_feed(
         UPDATE
             feeds
         SET
+            etag = iif(url = ?1, etag, NULL),
+            last_modified = iif(url = ?1, last_modified, NULL),
             url = ?1,
-            name = ?2
+            name = ?2,
+            interval = ?3,
+            import_categories = ?5,
+            keep_days = ?6,
+            keep_articles = ?7
         WHERE
-            id = ?3
+            id = ?4
         "#,
-        rusqlite::params![url, name, id],
+        rusqlite::params![
+            url,
+            name,
+            interval,
+            id,
+            import_categories,
+            keep_days,
+            keep_articles
+        ],
     )?;
+    if *import_categories && !imported {
+        import_categories_as_tags(&t, *id)?;
+    }
     t.commit()?;
     Ok((prev_folder_id, changed))
 }
 
+/// Finds a feed by its url, or by a url it was redirected from.
+pub fn find_feed_by_url(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    url: &str,
+) -> Result<Option<(u64, String)>> {
+    let feed = conn
+        .query_row(
+            r#"
+            SELECT
+                id,
+                name
+            FROM
+                feeds
+            WHERE
+                url = ?1
+            UNION ALL
+            SELECT
+                f.id,
+                f.name
+            FROM
+                feed_redirects AS r
+            INNER JOIN
+                feeds AS f
+            ON
+                f.id = r.feed_id
+            WHERE
+                r.from_url = ?1
+            LIMIT 1
+            "#,
+            [url],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(feed)
+}
+
+/// Moves a feed to another url, the caching validators belong to the
+/// previous one.
+pub fn update_feed_url(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    url: &str,
+) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        UPDATE
+            feeds
+        SET
+            url = ?2,
+            etag = NULL,
+            last_modified = NULL
+        WHERE
+            id = ?1
+        "#,
+        rusqlite::params![id, url],
+    )?;
+    Ok(changed)
+}
+
+/// Records a permanent redirect of a feed.
+pub fn create_feed_redirect(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    feed_id: u64,
+    from_url: &str,
+    to_url: &str,
+    status: u16,
+) -> Result<u64> {
+    let id = conn.query_row(
+        r#"
+        INSERT INTO feed_redirects (
+            feed_id,
+            from_url,
+            to_url,
+            status
+        )
+        VALUES (
+            ?1,
+            ?2,
+            ?3,
+            ?4
+        )
+        RETURNING
+            id
+        "#,
+        rusqlite::params![feed_id, from_url, to_url, status],
+        |row| row.get(0),
+    )?;
+    Ok(id)
+}
+
+/// A fetch succeeded, keeps the cache validators and resets the failures,
+/// `etag` and `last_modified` are kept when the server omits them.
+pub fn update_feed_fetched(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    etag: Option<&String>,
+    last_modified: Option<&String>,
+    status: u16,
+    fetched: i64,
+) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        UPDATE
+            feeds
+        SET
+            etag = ifnull(?1, etag),
+            last_modified = ifnull(?2, last_modified),
+            http_status = ?3,
+            last_fetched = ?4,
+            last_error = NULL,
+            failures = 0,
+            dead = 0,
+            retry_at = NULL
+        WHERE
+            id = ?5
+        "#,
+        rusqlite::params![etag, last_modified, status, fetched, id],
+    )?;
+    Ok(changed)
+}
+
+/// A fetch failed, returns the number of consecutive failures and whether the
+/// feed is dead after `dead_after` of them. `retry_at` is when the server
+/// asked to come back.
+pub fn update_feed_failed(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    error: &str,
+    status: Option<u16>,
+    retry_at: Option<i64>,
+    dead_after: u32,
+) -> Result<(u32, bool)> {
+    let failed = conn.query_row(
+        r#"
+        UPDATE
+            feeds
+        SET
+            http_status = ifnull(?2, http_status),
+            last_error = ?1,
+            failures = failures + 1,
+            dead = failures + 1 >= ?4,
+            retry_at = ?5
+        WHERE
+            id = ?3
+        RETURNING
+            failures,
+            dead
+        "#,
+        rusqlite::params![error, status, id, dead_after, retry_at],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(failed)
+}
+
+/// What a fetch learns about a feed from its document.
+pub struct FeedExt {
+    pub site: String,
+    pub kind: FeedType,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Returns the latest published time and the number of new articles.
 pub fn update_feed_ext_and_upsert_articles(
     conn: &mut PooledConnection<SqliteConnectionManager>,
     Feed { id, .. }: &Feed,
-    site: &String,
-    kind: FeedType,
-    title: Option<String>,
-    description: Option<String>,
+    ext: FeedExt,
     published: i64,
     authors: Vec<Person>,
     articles: Vec<Entry>,
-) -> Result<i64> {
-    update_feed_ext(conn, id, site, kind, title, description)?;
+) -> Result<(i64, usize)> {
+    update_feed_ext(conn, id, &ext)?;
 
-    upsert_articles(conn, id, site, published, authors, articles)
+    upsert_articles(conn, id, &ext.site, published, authors, articles)
 }
 
 fn update_feed_ext(
     conn: &mut PooledConnection<SqliteConnectionManager>,
     id: &u64,
-    site: &String,
-    kind: FeedType,
-    title: Option<String>,
-    description: Option<String>,
+    FeedExt {
+        site,
+        kind,
+        title,
+        description,
+    }: &FeedExt,
 ) -> Result<()> {
     let t = conn.transaction()?;
 
@@ -416,8 +886,9 @@ fn upsert_articles(
     feed_published: i64,
     authors: Vec<Person>,
     articles: Vec<Entry>,
-) -> Result<i64> {
+) -> Result<(i64, usize)> {
     let t = conn.transaction()?;
+    let mut new = 0;
 
     {
         let mut stmt = t.prepare_cached(
@@ -428,7 +899,8 @@ fn upsert_articles(
                 title,
                 content,
                 created,
-                updated 
+                updated,
+                categories
             )
             VALUES (
                 ?1,
@@ -436,13 +908,15 @@ fn upsert_articles(
                 ?3,
                 ?4,
                 ?5,
-                ?6
+                ?6,
+                ?7
             )
             ON CONFLICT(feed_id, url) DO 
             UPDATE
             SET
                 title = EXCLUDED.title,
                 content = EXCLUDED.content,
+                categories = EXCLUDED.categories,
                 -- created = ifnull(EXCLUDED.created, articles.created),
                 updated = ifnull(EXCLUDED.updated, ifnull(articles.updated, articles.created))
             RETURNING
@@ -490,12 +964,101 @@ fn upsert_articles(
             "#,
         )?;
 
+        // rules and categories only apply to new articles
+        let rules = enabled_rules(&t)?;
+        let import_categories: bool = t.query_row(
+            r#"
+            SELECT
+                import_categories
+            FROM
+                feeds
+            WHERE
+                id = ?1
+            "#,
+            [id],
+            |row| row.get(0),
+        )?;
+
+        let mut exists = t.prepare_cached(
+            r#"
+            SELECT
+                1
+            FROM
+                articles
+            WHERE
+                feed_id = ?1
+            AND
+                url = ?2
+            "#,
+        )?;
+
+        let mut pruned = t.prepare_cached(
+            r#"
+            SELECT
+                1
+            FROM
+                pruned_articles
+            WHERE
+                feed_id = ?1
+            AND
+                url = ?2
+            "#,
+        )?;
+
+        let mut sr = t.prepare_cached(
+            r#"
+            UPDATE
+                articles
+            SET
+                read = ?2 OR read,
+                read_at = iif(?2, ?4, read_at),
+                starred = iif(?3, ?4, starred),
+                folder_id = ifnull(?5, folder_id)
+            WHERE
+                id = ?1
+            "#,
+        )?;
+
+        let mut sg = t.prepare_cached(
+            r#"
+            INSERT INTO tags (
+                name
+            )
+            VALUES (
+                ?1
+            )
+            ON CONFLICT(name) DO 
+            UPDATE
+            SET
+                name = tags.name
+            RETURNING
+                id
+            "#,
+        )?;
+
+        let mut sag = t.prepare_cached(
+            r#"
+            INSERT INTO article_tags (
+                t,
+                g
+            )
+            VALUES (
+                ?1,
+                ?2
+            )
+            ON CONFLICT(t, g) DO 
+            NOTHING
+            "#,
+        )?;
+
+        let now = chrono::Utc::now().timestamp_millis();
+
         for article in articles {
             let updated = article.updated.map(|t| t.timestamp_millis());
             let published = article
                 .published
                 .map(|t| t.timestamp_millis())
-                .or_else(|| updated)
+                .or(updated)
                 .unwrap_or(feed_published);
 
             let updated = if let Some(updated) = updated {
@@ -504,36 +1067,109 @@ fn upsert_articles(
                 published
             };
 
+            let url = article
+                .links
+                .first()
+                .map(|link| link.href.to_owned())
+                // sometimes `article.id` is not a link
+                .or(Some(article.id))
+                .map(|path| {
+                    if path.starts_with(site) {
+                        path
+                    } else {
+                        let mut url = String::new();
+                        url.push_str(site.trim_end_matches('/'));
+                        url.push('/');
+                        url.push_str(path.trim_start_matches('/'));
+                        url
+                    }
+                })
+                .unwrap();
+            if pruned.exists(rusqlite::params![id, url])? {
+                continue;
+            }
+            let title = article.title.map(|t| t.content.trim().to_owned());
+            let content = article
+                .content
+                .and_then(|t| t.body)
+                .or_else(|| article.summary.map(|t| t.content));
+            let categories = article
+                .categories
+                .iter()
+                .map(|c| c.label.as_deref().unwrap_or(&c.term))
+                .collect::<Vec<_>>();
+
+            let known = exists.exists(rusqlite::params![id, url])?;
+            let outcome = if (rules.is_empty() && !import_categories) || known {
+                Outcome::default()
+            } else {
+                let authors = if article.authors.is_empty() {
+                    authors.first().into_iter().collect::<Vec<_>>()
+                } else {
+                    article.authors.iter().collect()
+                };
+                let mut outcome = rules::apply(
+                    &rules,
+                    &Candidate {
+                        feed_id: *id,
+                        title: title.as_deref().unwrap_or_default(),
+                        content: content.as_deref().unwrap_or_default(),
+                        url: &url,
+                        authors: authors.iter().map(|a| a.name.as_str()).collect(),
+                        categories: categories.clone(),
+                    },
+                );
+                if import_categories {
+                    for category in &categories {
+                        let category = category.trim();
+                        if !category.is_empty()
+                            && !outcome
+                                .tags
+                                .iter()
+                                .any(|t| t.eq_ignore_ascii_case(category))
+                        {
+                            outcome.tags.push(category.to_owned());
+                        }
+                    }
+                }
+                outcome
+            };
+            if outcome.drop {
+                continue;
+            }
+            if !known {
+                new += 1;
+            }
+
             let article_id: u64 = stmt.query_row(
                 rusqlite::params![
                     id,
-                    article
-                        .links
-                        .first()
-                        .map(|link| link.href.to_owned())
-                        // sometimes `article.id` is not a link
-                        .or(Some(article.id))
-                        .map(|path| if path.starts_with(site) {
-                            path
-                        } else {
-                            let mut url = String::new();
-                            url.push_str(site.trim_end_matches('/'));
-                            url.push('/');
-                            url.push_str(path.trim_start_matches('/'));
-                            url
-                        })
-                        .unwrap(),
-                    article.title.map(|t| t.content.trim().to_owned()),
-                    article
-                        .content
-                        .and_then(|t| t.body)
-                        .or_else(|| article.summary.map(|t| t.content)),
+                    url,
+                    title,
+                    content,
                     published,
                     updated,
+                    (!categories.is_empty())
+                        .then(|| serde_json::to_string(&categories))
+                        .transpose()?,
                 ],
                 |row| row.get(0),
             )?;
 
+            if outcome != Outcome::default() {
+                sr.execute(rusqlite::params![
+                    article_id,
+                    outcome.read,
+                    outcome.star,
+                    now,
+                    outcome.folder
+                ])?;
+                for tag in &outcome.tags {
+                    let tag_id: u64 = sg.query_row([tag], |row| row.get(0))?;
+                    sag.execute([article_id, tag_id])?;
+                }
+            }
+
             if article.authors.is_empty() {
                 if let Some(author) = authors.first() {
                     let author_id: u64 = sa.query_row(
@@ -570,84 +1206,1101 @@ fn upsert_articles(
     }
 
     t.commit()?;
-    Ok(feed_published)
+    Ok((feed_published, new))
 }
 
-pub fn find_articles_by_feed(
-    conn: &mut PooledConnection<SqliteConnectionManager>,
-    feed: &Feed,
-) -> Result<Vec<Article>> {
-    let articles = conn
-        .prepare_cached(
-            r#"
-            SELECT
-                id,
-                url,
-                title,
-                ifnull(content, ''),
-                created,
-                updated,
-                feed_id,
-                (
-                    SELECT 
-                        (CASE count(a.id)
-                        WHEN 0 THEN NULL
-                        ELSE json_group_array(
-                            json_object(
-                                'id',
-                                a.id,
-                                'name',
-                                a.name
-                            )
-                        )
-                        END)
-                    FROM
-                        authors as a
-                    JOIN
-                        article_authors AS aa
-                    ON
-                        aa.a = a.id
-                    AND
-                        aa.t = t.id
-                    GROUP BY
-                        aa.t
-                    ORDER BY
-                        aa.a
-                ) AS authors
+/// Columns of an article, `t` is the `articles` table.
+const ARTICLE_COLUMNS: &str = r#"
+    t.id,
+    t.url,
+    t.title,
+    ifnull(t.content, ''),
+    t.created,
+    t.updated,
+    t.feed_id,
+    t.read,
+    t.read_at,
+    t.starred,
+    (
+        SELECT 
+            (CASE count(a.id)
+            WHEN 0 THEN NULL
+            ELSE json_group_array(
+                json_object(
+                    'id',
+                    a.id,
+                    'name',
+                    a.name
+                )
+            )
+            END)
+        FROM
+            authors as a
+        JOIN
+            article_authors AS aa
+        ON
+            aa.a = a.id
+        AND
+            aa.t = t.id
+        GROUP BY
+            aa.t
+        ORDER BY
+            aa.a
+    ) AS authors,
+    (
+        SELECT
+            json_group_array(g.name)
+        FROM
+            tags AS g
+        JOIN
+            article_tags AS ag
+        ON
+            ag.g = g.id
+        AND
+            ag.t = t.id
+    ) AS tags
+"#;
+
+fn article(row: &rusqlite::Row) -> rusqlite::Result<Article> {
+    Ok(Article {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        created: row.get(4)?,
+        updated: row.get(5)?,
+        feed_id: row.get(6)?,
+        read: row.get(7)?,
+        read_at: row.get(8)?,
+        starred: row.get(9)?,
+        authors: row
+            .get::<_, Option<serde_json::Value>>(10)?
+            .and_then(|v| serde_json::from_value(v).ok()),
+        tags: row
+            .get::<_, Option<serde_json::Value>>(11)?
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// Columns of an article in a list, `t` is the `articles` table.
+const SUMMARY_COLUMNS: &str = r#"
+    t.id,
+    t.url,
+    t.title,
+    t.created,
+    t.updated,
+    t.feed_id,
+    t.read,
+    t.starred
+"#;
+
+/// Articles `t` of the folder `?1`, articles moved by a rule are only in
+/// their folder.
+const FOLDER_ARTICLES: &str = r#"
+    (t.folder_id IS NULL AND t.feed_id IN (
+        SELECT
+            f
+        FROM
+            folder_feeds
+        WHERE
+            d = ?1
+    ) OR t.folder_id = ?1)
+"#;
+
+fn summary(row: &rusqlite::Row) -> rusqlite::Result<ArticleSummary> {
+    Ok(ArticleSummary {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        created: row.get(3)?,
+        updated: row.get(4)?,
+        feed_id: row.get(5)?,
+        read: row.get(6)?,
+        starred: row.get(7)?,
+    })
+}
+
+/// An article with its content and authors.
+pub fn find_article(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<Article> {
+    let article = conn
+        .prepare_cached(&format!(
+            r#"
+            SELECT
+                {ARTICLE_COLUMNS}
             FROM
                 articles AS t
             WHERE
-                feed_id = ?1
+                t.id = ?1
+            "#
+        ))?
+        .query_row([id], article)?;
+
+    Ok(article)
+}
+
+/// A page of articles after `after`, the latest first. Starred ones are
+/// sorted by star time, `since` is the start of today.
+pub fn find_timeline_articles(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    timeline: Timeline,
+    since: i64,
+    after: Option<Cursor>,
+    limit: usize,
+) -> Result<Vec<ArticleSummary>> {
+    let (filter, param) = match timeline {
+        Timeline::Search => return Ok(Vec::new()),
+        Timeline::Smart(id) => {
+            let filter = find_smart_folder(conn, id)?.filter;
+            return find_smart_folder_articles(conn, &filter, after, limit);
+        }
+        Timeline::Feed(id) => ("t.feed_id = ?1", id as i64),
+        Timeline::All => ("?1 = ?1", 0),
+        Timeline::Unread => ("t.read = 0 AND ?1 = ?1", 0),
+        Timeline::Today => ("max(t.created, t.updated) >= ?1", since),
+        Timeline::Folder(id) => (FOLDER_ARTICLES, id as i64),
+        Timeline::Starred => ("t.starred IS NOT NULL AND ?1 = ?1", 0),
+        Timeline::Tag(id) => (
+            r#"t.id IN (
+                SELECT
+                    t
+                FROM
+                    article_tags
+                WHERE
+                    g = ?1
+            )"#,
+            id as i64,
+        ),
+    };
+    // the same as `ArticleSummary::cursor`
+    let key = match timeline {
+        Timeline::Starred => "t.starred",
+        _ => "max(t.created, t.updated)",
+    };
+    let articles = conn
+        .prepare_cached(&format!(
+            r#"
+            SELECT
+                {SUMMARY_COLUMNS}
+            FROM
+                articles AS t
+            WHERE
+                {filter}
             AND
-                id > ?2
+                (?2 IS NULL OR ({key}, t.id) < (?2, ?3))
             ORDER BY
-                id
-            "#,
-        )?
+                {key} DESC, t.id DESC
+            LIMIT
+                ?4
+            "#
+        ))?
+        .query_map(
+            rusqlite::params![param, after.map(|c| c.key), after.map(|c| c.id), limit],
+            summary,
+        )
+        .map(|rows| rows.filter_map(Result::ok).collect::<Vec<_>>())?;
+
+    Ok(articles)
+}
+
+/// Articles matching an FTS5 `query`, the best matches first. An empty query
+/// matches every article, the latest first.
+pub fn search_articles(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    query: &str,
+    filters: &Filters,
+    limit: usize,
+) -> Result<Vec<Hit>> {
+    let author = filters
+        .author
+        .as_ref()
+        .map(|author| format!("authors : \"{}\"", author.replace('"', "\"\"")));
+    let query = [Some(query.to_owned()), author]
+        .into_iter()
+        .flatten()
+        .filter(|q| !q.is_empty())
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    // char(2) and char(3) are the highlight marks of `search`
+    let (source, matches, snippet, order) = if query.is_empty() {
+        (
+            "articles AS t",
+            "?1 IS NULL",
+            "''",
+            "max(t.created, t.updated) DESC",
+        )
+    } else {
+        (
+            "articles_fts JOIN articles AS t ON t.id = articles_fts.rowid",
+            "articles_fts MATCH ?1",
+            "snippet(articles_fts, -1, char(2), char(3), '…', 16)",
+            "bm25(articles_fts, 10.0, 1.0, 5.0)",
+        )
+    };
+    let hits = conn
+        .prepare_cached(&format!(
+            r#"
+            SELECT
+                {SUMMARY_COLUMNS},
+                {snippet}
+            FROM
+                {source}
+            JOIN
+                feeds AS f
+            ON
+                f.id = t.feed_id
+            WHERE
+                {matches}
+            AND
+                (?2 IS NULL OR f.name LIKE '%' || ?2 || '%')
+            AND
+                (?3 IS NULL OR t.feed_id IN (
+                    SELECT
+                        ff.f
+                    FROM
+                        folder_feeds AS ff
+                    JOIN
+                        folders AS d
+                    ON
+                        d.id = ff.d
+                    WHERE
+                        d.name LIKE '%' || ?3 || '%'
+                ))
+            AND
+                (?4 = 0 OR t.read = 0)
+            AND
+                (?5 IS NULL OR t.id IN (
+                    SELECT
+                        ag.t
+                    FROM
+                        article_tags AS ag
+                    JOIN
+                        tags AS g
+                    ON
+                        g.id = ag.g
+                    WHERE
+                        g.name = ?5
+                ))
+            ORDER BY
+                {order}
+            LIMIT
+                ?6
+            "#
+        ))?
         .query_map(
             rusqlite::params![
-                feed.id,
-                feed.articles
-                    .as_ref()
-                    .and_then(|articles| articles.last().map(|a| a.id))
-                    .unwrap_or(0)
+                (!query.is_empty()).then_some(query),
+                filters.feed,
+                filters.folder,
+                filters.unread,
+                filters.tag,
+                limit
             ],
             |row| {
-                Ok(Article {
-                    id: row.get(0)?,
-                    url: row.get(1)?,
-                    title: row.get(2)?,
-                    content: row.get(3)?,
-                    created: row.get(4)?,
-                    updated: row.get(5)?,
-                    feed_id: row.get(6)?,
-                    authors: row
-                        .get::<_, Option<serde_json::Value>>(7)?
-                        .and_then(|v| serde_json::from_value(v).ok()),
+                Ok(Hit {
+                    article: summary(row)?,
+                    snippet: row.get(8)?,
                 })
             },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(hits)
+}
+
+/// Conditions of a smart folder on articles `t`, `?1` is its filter as JSON
+/// and `?2` the FTS5 query of its keywords.
+const SMART_FILTER: &str = r#"
+    (?2 IS NULL OR t.id IN (
+        SELECT
+            rowid
+        FROM
+            articles_fts
+        WHERE
+            articles_fts MATCH ?2
+    ))
+    AND
+        (json_array_length(?1, '$.feeds') + json_array_length(?1, '$.folders') = 0
+        OR t.feed_id IN (
+            SELECT
+                value
+            FROM
+                json_each(?1, '$.feeds')
+        )
+        OR t.feed_id IN (
+            SELECT
+                ff.f
+            FROM
+                folder_feeds AS ff
+            JOIN
+                json_each(?1, '$.folders') AS d
+            ON
+                ff.d = d.value
+        ))
+    AND
+        (json_array_length(?1, '$.authors') = 0 OR t.id IN (
+            SELECT
+                aa.t
+            FROM
+                article_authors AS aa
+            JOIN
+                authors AS a
+            ON
+                a.id = aa.a
+            WHERE
+                lower(a.name) IN (
+                    SELECT
+                        lower(value)
+                    FROM
+                        json_each(?1, '$.authors')
+                )
+        ))
+    AND
+        (json_extract(?1, '$.read') IS NULL OR t.read = json_extract(?1, '$.read'))
+    AND
+        (json_extract(?1, '$.starred') IS NULL
+        OR (t.starred IS NOT NULL) = json_extract(?1, '$.starred'))
+    AND
+        (json_extract(?1, '$.since') IS NULL
+        OR max(t.created, t.updated) >= json_extract(?1, '$.since'))
+    AND
+        (json_extract(?1, '$.until') IS NULL
+        OR max(t.created, t.updated) < json_extract(?1, '$.until'))
+"#;
+
+/// The filter as JSON and the FTS5 query of its keywords.
+fn smart_filter_params(filter: &SmartFilter) -> Result<(String, Option<String>)> {
+    let keywords = search::parse(&filter.keywords).text;
+    Ok((
+        serde_json::to_string(filter)?,
+        (!keywords.is_empty()).then_some(keywords),
+    ))
+}
+
+/// A page of articles of a smart folder after `after`, the latest first.
+pub fn find_smart_folder_articles(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    filter: &SmartFilter,
+    after: Option<Cursor>,
+    limit: usize,
+) -> Result<Vec<ArticleSummary>> {
+    let (filter, keywords) = smart_filter_params(filter)?;
+    let articles = conn
+        .prepare_cached(&format!(
+            r#"
+            SELECT
+                {SUMMARY_COLUMNS}
+            FROM
+                articles AS t
+            WHERE
+                {SMART_FILTER}
+            AND
+                (?3 IS NULL OR (max(t.created, t.updated), t.id) < (?3, ?4))
+            ORDER BY
+                max(t.created, t.updated) DESC, t.id DESC
+            LIMIT
+                ?5
+            "#
+        ))?
+        .query_map(
+            rusqlite::params![
+                filter,
+                keywords,
+                after.map(|c| c.key),
+                after.map(|c| c.id),
+                limit
+            ],
+            summary,
         )
         .map(|rows| rows.filter_map(Result::ok).collect::<Vec<_>>())?;
 
     Ok(articles)
 }
+
+fn count_smart_folder_unread(conn: &Connection, filter: &SmartFilter) -> Result<usize> {
+    let (filter, keywords) = smart_filter_params(filter)?;
+    let unread = conn
+        .prepare_cached(&format!(
+            r#"
+            SELECT
+                count(*)
+            FROM
+                articles AS t
+            WHERE
+                t.read = 0
+            AND
+                {SMART_FILTER}
+            "#
+        ))?
+        .query_row(rusqlite::params![filter, keywords], |row| row.get(0))?;
+    Ok(unread)
+}
+
+/// Smart folders by name with their unread counts.
+pub fn fetch_smart_folders(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<SmartFolder>> {
+    let mut folders = conn
+        .prepare_cached(
+            r#"
+            SELECT
+                id,
+                name,
+                filter
+            FROM
+                smart_folders
+            ORDER BY
+                name
+            "#,
+        )?
+        .query_map([], smart_folder)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for folder in &mut folders {
+        folder.unread = count_smart_folder_unread(conn, &folder.filter)?;
+    }
+    Ok(folders)
+}
+
+fn find_smart_folder(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<SmartFolder> {
+    let folder = conn.query_row(
+        r#"
+        SELECT
+            id,
+            name,
+            filter
+        FROM
+            smart_folders
+        WHERE
+            id = ?1
+        "#,
+        [id],
+        smart_folder,
+    )?;
+    Ok(folder)
+}
+
+fn smart_folder(row: &rusqlite::Row) -> rusqlite::Result<SmartFolder> {
+    Ok(SmartFolder {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        filter: serde_json::from_value(row.get::<_, serde_json::Value>(2)?).unwrap_or_default(),
+        unread: 0,
+    })
+}
+
+pub fn create_smart_folder(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    SmartFolder { name, filter, .. }: &SmartFolder,
+) -> Result<u64> {
+    let id = conn.query_row(
+        r#"
+        INSERT INTO smart_folders (
+            name,
+            filter
+        )
+        VALUES (
+            ?1,
+            ?2
+        )
+        RETURNING
+            id
+        "#,
+        rusqlite::params![name, serde_json::to_string(filter)?],
+        |row| row.get(0),
+    )?;
+    Ok(id)
+}
+
+pub fn update_smart_folder(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    SmartFolder {
+        id, name, filter, ..
+    }: &SmartFolder,
+) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        UPDATE
+            smart_folders
+        SET
+            name = ?2,
+            filter = ?3,
+            updated_at = CAST(
+                ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+            )
+        WHERE
+            id = ?1
+        "#,
+        rusqlite::params![id, name, serde_json::to_string(filter)?],
+    )?;
+    Ok(changed)
+}
+
+pub fn delete_smart_folder(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        DELETE FROM
+            smart_folders
+        WHERE
+            id = ?1
+        "#,
+        [id],
+    )?;
+    Ok(changed)
+}
+
+/// Stars an article at `at`, `None` removes the star.
+pub fn star_article(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    at: Option<i64>,
+) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        UPDATE
+            articles
+        SET
+            starred = ?2
+        WHERE
+            id = ?1
+        "#,
+        rusqlite::params![id, at],
+    )?;
+    Ok(changed)
+}
+
+/// Marks unread articles of `scope` as read, only the ones published before
+/// `before` when it is given. Returns the number of changed articles by feed.
+pub fn mark_read(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    scope: Scope,
+    before: Option<i64>,
+    at: i64,
+) -> Result<HashMap<u64, usize>> {
+    let (filter, id) = match scope {
+        Scope::Article(id) => ("id = ?1", id),
+        Scope::Feed(id) => ("feed_id = ?1", id),
+        Scope::Folder(id) => (FOLDER_ARTICLES, id),
+        Scope::All => ("?1 = ?1", 0),
+    };
+    let mut changed = HashMap::new();
+    conn.prepare(&format!(
+        r#"
+        UPDATE
+            articles AS t
+        SET
+            read = 1,
+            read_at = ?3
+        WHERE
+            read = 0
+        AND
+            {filter}
+        AND
+            (?2 IS NULL OR created < ?2)
+        RETURNING
+            feed_id
+        "#
+    ))?
+    .query_map(rusqlite::params![id, before, at], |row| {
+        row.get::<_, u64>(0)
+    })?
+    .try_for_each(|feed_id| {
+        *changed.entry(feed_id?).or_default() += 1;
+        Ok::<_, rusqlite::Error>(())
+    })?;
+    Ok(changed)
+}
+
+pub fn mark_unread(conn: &mut PooledConnection<SqliteConnectionManager>, id: u64) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        UPDATE
+            articles
+        SET
+            read = 0,
+            read_at = NULL
+        WHERE
+            id = ?1
+        AND
+            read = 1
+        "#,
+        [id],
+    )?;
+    Ok(changed)
+}
+
+pub fn count_unread(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    feed_id: u64,
+) -> Result<usize> {
+    let unread = conn.query_row(
+        r#"
+        SELECT
+            count(*)
+        FROM
+            articles
+        WHERE
+            feed_id = ?1
+        AND
+            read = 0
+        "#,
+        [feed_id],
+        |row| row.get(0),
+    )?;
+    Ok(unread)
+}
+
+fn rule(row: &rusqlite::Row) -> rusqlite::Result<Rule> {
+    Ok(Rule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        enabled: row.get(2)?,
+        conditions: serde_json::from_value(row.get::<_, serde_json::Value>(3)?).unwrap_or_default(),
+        actions: serde_json::from_value(row.get::<_, serde_json::Value>(4)?).unwrap_or_default(),
+    })
+}
+
+/// Rules in the order they run.
+pub fn fetch_rules(conn: &mut PooledConnection<SqliteConnectionManager>) -> Result<Vec<Rule>> {
+    let rules = conn
+        .prepare_cached(
+            r#"
+            SELECT
+                id,
+                name,
+                enabled,
+                conditions,
+                actions
+            FROM
+                rules
+            ORDER BY
+                id
+            "#,
+        )?
+        .query_map([], rule)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+fn enabled_rules(conn: &Connection) -> Result<Vec<Rule>> {
+    let rules = conn
+        .prepare_cached(
+            r#"
+            SELECT
+                id,
+                name,
+                enabled,
+                conditions,
+                actions
+            FROM
+                rules
+            WHERE
+                enabled = 1
+            ORDER BY
+                id
+            "#,
+        )?
+        .query_map([], rule)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+/// Creates the rule when its id is `0`, returns its id.
+pub fn save_rule(conn: &mut PooledConnection<SqliteConnectionManager>, rule: &Rule) -> Result<u64> {
+    let conditions = serde_json::to_string(&rule.conditions)?;
+    let actions = serde_json::to_string(&rule.actions)?;
+    if rule.id == 0 {
+        let id = conn.query_row(
+            r#"
+            INSERT INTO rules (
+                name,
+                enabled,
+                conditions,
+                actions
+            )
+            VALUES (
+                ?1,
+                ?2,
+                ?3,
+                ?4
+            )
+            RETURNING
+                id
+            "#,
+            rusqlite::params![rule.name, rule.enabled, conditions, actions],
+            |row| row.get(0),
+        )?;
+        return Ok(id);
+    }
+    conn.execute(
+        r#"
+        UPDATE
+            rules
+        SET
+            name = ?2,
+            enabled = ?3,
+            conditions = ?4,
+            actions = ?5,
+            updated_at = CAST(
+                ROUND((julianday('now') - 2440587.5) * 86400000) As INTEGER
+            )
+        WHERE
+            id = ?1
+        "#,
+        rusqlite::params![rule.id, rule.name, rule.enabled, conditions, actions],
+    )?;
+    Ok(rule.id)
+}
+
+pub fn delete_rule(conn: &mut PooledConnection<SqliteConnectionManager>, id: u64) -> Result<usize> {
+    let changed = conn.execute(
+        r#"
+        DELETE FROM
+            rules
+        WHERE
+            id = ?1
+        "#,
+        [id],
+    )?;
+    Ok(changed)
+}
+
+/// Existing articles `rule` would have matched, keeps the latest `limit` ones.
+/// Matches a rule against the latest `scan` articles, `limit` of the matched
+/// ones are kept.
+pub fn dry_run_rule(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    rule: &Rule,
+    scan: usize,
+    limit: usize,
+) -> Result<rules::DryRun> {
+    let mut stmt = conn.prepare_cached(&format!(
+        r#"
+        SELECT
+            {ARTICLE_COLUMNS},
+            t.categories
+        FROM
+            articles AS t
+        ORDER BY
+            max(t.created, t.updated) DESC,
+            t.id DESC
+        LIMIT ?1
+        "#
+    ))?;
+    // one more tells whether there are older ones
+    let mut rows = stmt.query([scan + 1])?;
+    let mut dry_run = rules::DryRun::default();
+    while let Some(row) = rows.next()? {
+        if dry_run.scanned == scan {
+            dry_run.truncated = true;
+            break;
+        }
+        dry_run.scanned += 1;
+        let mut article = article(row)?;
+        let categories = row
+            .get::<_, Option<serde_json::Value>>(12)?
+            .and_then(|c| serde_json::from_value::<Vec<String>>(c).ok())
+            .unwrap_or_default();
+        let matched = rule.matches(&Candidate {
+            feed_id: article.feed_id,
+            title: &article.title,
+            content: &article.content,
+            url: &article.url,
+            authors: article
+                .authors
+                .iter()
+                .flatten()
+                .map(|a| a.name.as_str())
+                .collect(),
+            categories: categories.iter().map(String::as_str).collect(),
+        });
+        if !matched {
+            continue;
+        }
+        dry_run.matched += 1;
+        if dry_run.articles.len() < limit {
+            article.content.clear();
+            dry_run.articles.push(article);
+        }
+    }
+    Ok(dry_run)
+}
+
+/// Tags of articles with their unread counts, by name.
+pub fn fetch_tags(conn: &mut PooledConnection<SqliteConnectionManager>) -> Result<Vec<Tag>> {
+    let tags = conn
+        .prepare_cached(
+            r#"
+            SELECT
+                g.id,
+                g.name,
+                sum(t.read = 0)
+            FROM
+                tags AS g
+            JOIN
+                article_tags AS ag
+            ON
+                ag.g = g.id
+            JOIN
+                articles AS t
+            ON
+                t.id = ag.t
+            GROUP BY
+                g.id
+            ORDER BY
+                g.name
+            "#,
+        )?
+        .query_map([], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                unread: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+/// Replaces the tags of an article, returns them with the case of the
+/// existing tags.
+pub fn set_article_tags(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    tags: &[String],
+) -> Result<Vec<String>> {
+    let t = conn.transaction()?;
+    let mut names = Vec::new();
+    {
+        t.execute(
+            r#"
+            DELETE FROM
+                article_tags
+            WHERE
+                t = ?1
+            "#,
+            [id],
+        )?;
+
+        let mut sg = t.prepare_cached(
+            r#"
+            INSERT INTO tags (
+                name
+            )
+            VALUES (
+                ?1
+            )
+            ON CONFLICT(name) DO 
+            UPDATE
+            SET
+                name = tags.name
+            RETURNING
+                id,
+                name
+            "#,
+        )?;
+
+        let mut sag = t.prepare_cached(
+            r#"
+            INSERT INTO article_tags (
+                t,
+                g
+            )
+            VALUES (
+                ?1,
+                ?2
+            )
+            ON CONFLICT(t, g) DO 
+            NOTHING
+            "#,
+        )?;
+
+        for tag in tags.iter().map(|tag| tag.trim()).filter(|t| !t.is_empty()) {
+            let (tag_id, name): (u64, String) =
+                sg.query_row([tag], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            if sag.execute([id, tag_id])? > 0 {
+                names.push(name);
+            }
+        }
+    }
+    t.commit()?;
+    Ok(names)
+}
+
+/// Tags the stored articles of a feed with their categories.
+fn import_categories_as_tags(conn: &Connection, feed_id: u64) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO tags (
+            name
+        )
+        SELECT DISTINCT
+            trim(c.value)
+        FROM
+            articles AS t,
+            json_each(t.categories) AS c
+        WHERE
+            t.feed_id = ?1
+        AND
+            trim(c.value) <> ''
+        ON CONFLICT(name) DO 
+        NOTHING
+        "#,
+        [feed_id],
+    )?;
+    conn.execute(
+        r#"
+        INSERT INTO article_tags (
+            t,
+            g
+        )
+        SELECT
+            t.id,
+            g.id
+        FROM
+            articles AS t,
+            json_each(t.categories) AS c
+        JOIN
+            tags AS g
+        ON
+            g.name = trim(c.value)
+        WHERE
+            t.feed_id = ?1
+        ON CONFLICT(t, g) DO 
+        NOTHING
+        "#,
+        [feed_id],
+    )?;
+    Ok(())
+}
+
+/// Deletes read and unstarred articles older than the days or beyond the
+/// number of articles kept by their feeds, feeds without their own retention
+/// follow `keep_days` and `keep_articles`. Returns the ids of the deleted
+/// articles.
+pub fn prune_articles(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    now: i64,
+    keep_days: u32,
+    keep_articles: u32,
+) -> Result<HashSet<u64>> {
+    let t = conn.transaction()?;
+    let mut ids = HashSet::new();
+
+    {
+        let mut stmt = t.prepare_cached(
+            r#"
+            WITH f AS (
+                SELECT
+                    id,
+                    ifnull(keep_days, ?2) AS days,
+                    ifnull(keep_articles, ?3) AS articles
+                FROM
+                    feeds
+            ),
+            t AS (
+                SELECT
+                    t.id,
+                    t.feed_id,
+                    t.url,
+                    t.read,
+                    t.starred,
+                    max(t.created, t.updated) AS at,
+                    row_number() OVER (
+                        PARTITION BY
+                            t.feed_id
+                        ORDER BY
+                            max(t.created, t.updated) DESC,
+                            t.id DESC
+                    ) AS n,
+                    f.days,
+                    f.articles
+                FROM
+                    articles AS t
+                JOIN
+                    f
+                ON
+                    f.id = t.feed_id
+            )
+            SELECT
+                id,
+                feed_id,
+                url
+            FROM
+                t
+            WHERE
+                read = 1
+            AND
+                starred IS NULL
+            AND
+                (
+                    (days > 0 AND at < ?1 - days * 86400000)
+                OR
+                    (articles > 0 AND n > articles)
+                )
+            "#,
+        )?;
+
+        let mut sp = t.prepare_cached(
+            r#"
+            INSERT INTO pruned_articles (
+                feed_id,
+                url
+            )
+            VALUES (
+                ?1,
+                ?2
+            )
+            ON CONFLICT(feed_id, url) DO 
+            NOTHING
+            "#,
+        )?;
+
+        let mut sd = t.prepare_cached(
+            r#"
+            DELETE FROM
+                articles
+            WHERE
+                id = ?1
+            "#,
+        )?;
+
+        let pruned = stmt
+            .query_map(rusqlite::params![now, keep_days, keep_articles], |row| {
+                Ok((
+                    row.get::<_, u64>(0)?,
+                    row.get::<_, u64>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, feed_id, url) in pruned {
+            sp.execute(rusqlite::params![feed_id, url])?;
+            sd.execute([id])?;
+            ids.insert(id);
+        }
+    }
+
+    t.commit()?;
+    Ok(ids)
+}
+
+/// Gives the free pages back to the file system, returns the freed bytes.
+pub fn vacuum(conn: &mut PooledConnection<SqliteConnectionManager>) -> Result<u64> {
+    let pages = |conn: &Connection| -> rusqlite::Result<u64> {
+        conn.query_row("PRAGMA page_count", [], |row| row.get(0))
+    };
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let before = pages(conn)?;
+    conn.execute_batch("PRAGMA incremental_vacuum;")?;
+    let after = pages(conn)?;
+    Ok(before.saturating_sub(after) * page_size)
+}
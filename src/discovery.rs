@@ -0,0 +1,111 @@
+//! Finds feeds of a website.
+
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::utils;
+
+/// Types of `<link rel="alternate">` pointing to a feed.
+const FEED_TYPES: [&str; 4] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/json",
+];
+
+/// Where feeds usually live when a page does not declare them.
+const COMMON_PATHS: [&str; 6] = [
+    "/feed",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.xml",
+    "/rss",
+];
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub url: String,
+    pub title: Option<String>,
+    /// the declared or served type, `None` for a guess which is not checked yet
+    pub kind: Option<String>,
+}
+
+/// Feeds declared by `<link rel="alternate">`, then links of the page which
+/// look like feeds, relative urls are resolved against `base`.
+pub fn parse_candidates(base: &Url, html: &str) -> Vec<Candidate> {
+    let document = Html::parse_document(html);
+    let base = Selector::parse("base[href]")
+        .ok()
+        .and_then(|s| document.select(&s).next())
+        .and_then(|e| e.value().attr("href"))
+        .and_then(|href| base.join(href).ok())
+        .unwrap_or_else(|| base.clone());
+
+    let mut candidates = Vec::<Candidate>::new();
+    let mut push = |candidate: Candidate| {
+        if !candidates.iter().any(|c| c.url == candidate.url) {
+            candidates.push(candidate);
+        }
+    };
+
+    if let Ok(selector) = Selector::parse(r#"link[rel~="alternate"][href][type]"#) {
+        for link in document.select(&selector) {
+            let link = link.value();
+            let kind = link.attr("type").unwrap_or_default().trim().to_lowercase();
+            if !FEED_TYPES.contains(&kind.as_str()) {
+                continue;
+            }
+            let Some(url) = link
+                .attr("href")
+                .and_then(|href| base.join(href.trim()).ok())
+            else {
+                continue;
+            };
+            push(Candidate {
+                url: url.to_string(),
+                title: link
+                    .attr("title")
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(ToOwned::to_owned),
+                kind: Some(kind),
+            });
+        }
+    }
+
+    if let Ok(selector) = Selector::parse("a[href]") {
+        for a in document.select(&selector) {
+            let Some(url) = a
+                .value()
+                .attr("href")
+                .and_then(|href| base.join(href.trim()).ok())
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+                .filter(|url| utils::is_feed_url(url.as_str()))
+            else {
+                continue;
+            };
+            let title = a.text().collect::<String>();
+            push(Candidate {
+                url: url.to_string(),
+                title: Some(title.trim().to_owned()).filter(|t| !t.is_empty()),
+                kind: None,
+            });
+        }
+    }
+
+    candidates
+}
+
+/// Common feed paths of the site of `base`.
+pub fn common_paths(base: &Url) -> Vec<Candidate> {
+    COMMON_PATHS
+        .iter()
+        .filter_map(|path| base.join(path).ok())
+        .map(|url| Candidate {
+            url: url.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    sync::Arc,
+    thread,
+};
+
+use tokio::sync::oneshot;
+
+use crate::{errors::Gone, service::Pending};
+
+use super::Block;
+
+/// articles kept parsed
+const CAPACITY: usize = 16;
+
+/// The parsed content of an article.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    pub id: u64,
+    /// hash of the content it is parsed from
+    hash: u64,
+    pub blocks: Arc<Vec<Block>>,
+}
+
+impl Document {
+    pub fn parse(id: u64, content: &str) -> Self {
+        let mut events = Vec::new();
+        super::parser(htmlize::unescape(content), &mut events);
+        Self {
+            id,
+            hash: hash(content),
+            blocks: Arc::new(super::build(&events)),
+        }
+    }
+
+    /// Parses the content of an article on another thread.
+    pub fn parse_in_background(id: u64, content: String) -> Pending<Self, Gone> {
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            let _ = tx.send(Ok(Self::parse(id, &content)));
+        });
+        Pending::new(rx)
+    }
+
+    /// Parsed from this content of the article.
+    pub fn is_of(&self, id: u64, content: &str) -> bool {
+        self.id == id && self.hash == hash(content)
+    }
+}
+
+/// Recently opened articles, parsed again once their content changes. The
+/// least recently used one is dropped first.
+#[derive(Debug, Default)]
+pub struct Documents(VecDeque<Document>);
+
+impl Documents {
+    pub fn get(&mut self, id: u64, content: &str) -> Option<Document> {
+        let i = self.0.iter().position(|d| d.id == id)?;
+        let document = self.0.remove(i)?;
+        if !document.is_of(id, content) {
+            return None;
+        }
+        self.0.push_back(document.clone());
+        Some(document)
+    }
+
+    pub fn insert(&mut self, document: Document) {
+        self.0.retain(|d| d.id != document.id);
+        self.0.push_back(document);
+        if self.0.len() > CAPACITY {
+            self.0.pop_front();
+        }
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,370 @@
+//! A tree of blocks and inlines built from the events of the parser, which
+//! the renderer draws without keeping any state between events.
+
+use std::{mem, slice::Iter};
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    Paragraph(Vec<Inline>),
+    /// level from 1 to 6
+    Heading(u8, Vec<Inline>),
+    /// items of blocks, `Some` numbers them from the start
+    List(Option<u64>, Vec<Vec<Block>>),
+    Quote(Vec<Block>),
+    Code {
+        lang: Option<String>,
+        text: String,
+    },
+    Table {
+        head: Vec<Vec<Inline>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    /// an image on its own
+    Figure(Image),
+    Rule,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inline {
+    Text(String, Marks),
+    Code(String),
+    Link {
+        href: String,
+        title: String,
+        content: Vec<Inline>,
+    },
+    Image(Image),
+    /// a line break
+    Break,
+}
+
+/// Emphasis of a text span.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Marks {
+    pub strong: bool,
+    pub italics: bool,
+    pub strikethrough: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    pub url: String,
+    pub alt: String,
+}
+
+type Events<'a, 'b> = Iter<'a, Event<'b>>;
+
+/// Builds the blocks of parsed events. Unbalanced or unexpected events are
+/// skipped, their text is kept where possible.
+pub fn build(events: &[Event<'_>]) -> Vec<Block> {
+    blocks(&mut events.iter(), None)
+}
+
+impl Inline {
+    /// The text of an inline and its children.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text, _) | Self::Code(text) => text.clone(),
+            Self::Link { content, .. } => text(content),
+            Self::Image(image) => image.alt.clone(),
+            Self::Break => "\n".to_owned(),
+        }
+    }
+}
+
+/// The text of inlines.
+pub fn text(inlines: &[Inline]) -> String {
+    inlines.iter().map(Inline::text).collect()
+}
+
+fn same(a: &Tag<'_>, b: &Tag<'_>) -> bool {
+    mem::discriminant(a) == mem::discriminant(b)
+}
+
+/// Blocks until the end of `parent`, loose inlines become paragraphs.
+fn blocks(events: &mut Events, parent: Option<&Tag<'_>>) -> Vec<Block> {
+    let mut children = Vec::new();
+    let mut loose = Vec::new();
+    while let Some(event) = events.next() {
+        let block = match event {
+            Event::End(tag) if parent.is_some_and(|p| same(p, tag)) => break,
+            // unbalanced
+            Event::End(_) => continue,
+            Event::Start(tag @ Tag::Paragraph) => {
+                Block::Paragraph(normalize(inlines(events, tag, Marks::default())))
+            }
+            Event::Start(tag @ Tag::Heading(level, ..)) => Block::Heading(
+                *level as u8,
+                normalize(inlines(events, tag, Marks::default())),
+            ),
+            Event::Start(tag @ Tag::BlockQuote) => Block::Quote(blocks(events, Some(tag))),
+            Event::Start(Tag::List(start)) => Block::List(*start, items(events)),
+            Event::Start(Tag::CodeBlock(kind)) => Block::Code {
+                lang: match kind {
+                    CodeBlockKind::Fenced(lang) if !lang.is_empty() => Some(lang.to_string()),
+                    _ => None,
+                },
+                text: code(events),
+            },
+            Event::Start(Tag::Table(_)) => table(events),
+            Event::Start(tag @ Tag::Image(_, url, title)) => {
+                Block::Figure(image(events, tag, url, title))
+            }
+            // out of their parents
+            Event::Start(
+                tag @ (Tag::Item
+                | Tag::FootnoteDefinition(_)
+                | Tag::TableHead
+                | Tag::TableRow
+                | Tag::TableCell),
+            ) => {
+                paragraph(&mut children, &mut loose);
+                children.extend(blocks(events, Some(tag)));
+                continue;
+            }
+            Event::Rule => Block::Rule,
+            event => {
+                inline(events, event, Marks::default(), &mut loose);
+                continue;
+            }
+        };
+        paragraph(&mut children, &mut loose);
+        children.push(block);
+    }
+    paragraph(&mut children, &mut loose);
+    children
+}
+
+/// Loose inlines as a paragraph, unless they are blank.
+fn paragraph(blocks: &mut Vec<Block>, loose: &mut Vec<Inline>) {
+    let inlines = normalize(mem::take(loose));
+    if !inlines.is_empty() {
+        blocks.push(Block::Paragraph(inlines));
+    }
+}
+
+/// Items of a list until its end.
+fn items(events: &mut Events) -> Vec<Vec<Block>> {
+    let mut items = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::End(Tag::List(_)) => break,
+            Event::Start(tag @ Tag::Item) => items.push(blocks(events, Some(tag))),
+            // outside of an item, kept as one
+            Event::Start(tag) => items.push(blocks(events, Some(tag))),
+            _ => {}
+        }
+    }
+    items
+}
+
+/// Text of a code block until its end.
+fn code(events: &mut Events) -> String {
+    let mut code = String::new();
+    for event in events.by_ref() {
+        match event {
+            Event::End(Tag::CodeBlock(_)) => break,
+            Event::Text(text) | Event::Code(text) | Event::Html(text) => code.push_str(text),
+            Event::SoftBreak | Event::HardBreak => code.push('\n'),
+            _ => {}
+        }
+    }
+    code
+}
+
+/// Head and rows of a table until its end.
+fn table(events: &mut Events) -> Block {
+    let mut head = Vec::new();
+    let mut rows = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::End(Tag::Table(_)) => break,
+            Event::Start(tag @ Tag::TableHead) => head = cells(events, tag),
+            Event::Start(tag @ Tag::TableRow) => rows.push(cells(events, tag)),
+            _ => {}
+        }
+    }
+    Block::Table { head, rows }
+}
+
+/// Cells of a row until the end of `parent`.
+fn cells(events: &mut Events, parent: &Tag<'_>) -> Vec<Vec<Inline>> {
+    let mut cells = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::End(tag) if same(parent, tag) => break,
+            Event::Start(tag @ Tag::TableCell) => {
+                cells.push(normalize(inlines(events, tag, Marks::default())))
+            }
+            _ => {}
+        }
+    }
+    cells
+}
+
+/// Inlines until the end of `parent`, nested blocks are flattened.
+fn inlines(events: &mut Events, parent: &Tag<'_>, marks: Marks) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::End(tag) if same(parent, tag) => break,
+            event => inline(events, event, marks, &mut inlines),
+        }
+    }
+    inlines
+}
+
+/// An image until the end of `parent`, its children are the alt text.
+///
+/// The parser sorts the attributes of an `img` by name, so with an alt text
+/// it comes first and the source second.
+fn image(events: &mut Events, parent: &Tag<'_>, url: &str, title: &str) -> Image {
+    let (url, title) = match title {
+        "" => (url, title),
+        _ => (title, url),
+    };
+    let alt = text(&inlines(events, parent, Marks::default()));
+    let alt = collapse(&alt, &mut true);
+    Image {
+        url: url.to_owned(),
+        alt: match alt.trim() {
+            "" => title.trim().to_owned(),
+            alt => alt.to_owned(),
+        },
+    }
+}
+
+/// Adds an inline event and its children.
+fn inline(events: &mut Events, event: &Event<'_>, marks: Marks, inlines: &mut Vec<Inline>) {
+    match event {
+        Event::Start(tag @ Tag::Strong) => inlines.extend(self::inlines(
+            events,
+            tag,
+            Marks {
+                strong: true,
+                ..marks
+            },
+        )),
+        Event::Start(tag @ Tag::Emphasis) => inlines.extend(self::inlines(
+            events,
+            tag,
+            Marks {
+                italics: true,
+                ..marks
+            },
+        )),
+        Event::Start(tag @ Tag::Strikethrough) => inlines.extend(self::inlines(
+            events,
+            tag,
+            Marks {
+                strikethrough: true,
+                ..marks
+            },
+        )),
+        Event::Start(tag @ Tag::Link(_, href, title)) => inlines.push(Inline::Link {
+            href: href.to_string(),
+            title: title.to_string(),
+            content: self::inlines(events, tag, marks),
+        }),
+        Event::Start(tag @ Tag::Image(_, url, title)) => {
+            inlines.push(Inline::Image(image(events, tag, url, title)))
+        }
+        Event::Start(tag) => inlines.extend(self::inlines(events, tag, marks)),
+        Event::Text(text) => inlines.push(Inline::Text(text.to_string(), marks)),
+        Event::Code(code) => inlines.push(Inline::Code(code.to_string())),
+        Event::FootnoteReference(name) => inlines.push(Inline::Text(format!("[{name}]"), marks)),
+        Event::TaskListMarker(done) => inlines.push(Inline::Text(
+            if *done { "☑ " } else { "☐ " }.to_owned(),
+            marks,
+        )),
+        Event::SoftBreak => inlines.push(Inline::Text(" ".to_owned(), marks)),
+        Event::HardBreak => inlines.push(Inline::Break),
+        Event::End(_) | Event::Html(_) | Event::Rule => {}
+    }
+}
+
+/// Collapses white space like HTML does, joins texts with the same marks
+/// and trims the ends of lines.
+fn normalize(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut inlines = join(inlines);
+    collapse_all(&mut inlines, &mut true);
+    let mut end = true;
+    for inline in inlines.iter_mut().rev() {
+        match inline {
+            Inline::Text(text, _) if end => {
+                let len = text.trim_end().len();
+                text.truncate(len);
+                end = text.is_empty();
+            }
+            Inline::Break => end = true,
+            _ => end = false,
+        }
+    }
+    retain(&mut inlines);
+    inlines
+}
+
+fn join(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut joined: Vec<Inline> = Vec::with_capacity(inlines.len());
+    for inline in inlines {
+        match (joined.last_mut(), inline) {
+            (Some(Inline::Text(last, a)), Inline::Text(text, b)) if *a == b => last.push_str(&text),
+            (
+                _,
+                Inline::Link {
+                    href,
+                    title,
+                    content,
+                },
+            ) => joined.push(Inline::Link {
+                href,
+                title,
+                content: join(content),
+            }),
+            (_, inline) => joined.push(inline),
+        }
+    }
+    joined
+}
+
+fn collapse_all(inlines: &mut [Inline], space: &mut bool) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(text, _) => *text = collapse(text, space),
+            Inline::Link { content, .. } => collapse_all(content, space),
+            Inline::Break => *space = true,
+            _ => *space = false,
+        }
+    }
+}
+
+/// Drops empty texts.
+fn retain(inlines: &mut Vec<Inline>) {
+    inlines.retain_mut(|inline| match inline {
+        Inline::Text(text, _) => !text.is_empty(),
+        Inline::Link { content, .. } => {
+            retain(content);
+            true
+        }
+        _ => true,
+    });
+}
+
+/// Runs of white space as one space, `space` is true after one.
+fn collapse(text: &str, space: &mut bool) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !*space {
+                collapsed.push(' ');
+            }
+            *space = true;
+        } else {
+            collapsed.push(c);
+            *space = false;
+        }
+    }
+    collapsed
+}
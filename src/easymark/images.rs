@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use eframe::egui::ColorImage;
+use egui_extras::RetainedImage;
+use image::RgbaImage;
+
+use crate::{
+    errors::ImageError,
+    service::{Command, Pending},
+    Store,
+};
+
+/// An image of the opened article.
+pub enum Slot {
+    /// drawn once, to be requested after the frame
+    Wanted,
+    Loading(Pending<RgbaImage, ImageError>),
+    Loaded(RetainedImage),
+    /// the error, shown on hover
+    Failed(String),
+}
+
+/// Images of the opened article, loaded by the worker and dropped once
+/// another article is opened.
+#[derive(Default)]
+pub struct Images {
+    /// the url of the article, relative urls of images are joined to it
+    base: Option<url::Url>,
+    slots: HashMap<String, Slot>,
+}
+
+impl Images {
+    /// Images of another article, the ones of the previous article are
+    /// dropped.
+    pub fn open(&mut self, url: &str) {
+        let base = url::Url::parse(url).ok();
+        if base != self.base {
+            self.base = base;
+            self.slots.clear();
+        }
+    }
+
+    /// The slot of an image, it is wanted the first time. `None` when its url
+    /// is not valid.
+    pub fn get(&mut self, src: &str) -> Option<&Slot> {
+        let url = match &self.base {
+            Some(base) => base.join(src),
+            None => url::Url::parse(src),
+        }
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+        Some(self.slots.entry(url.into()).or_insert(Slot::Wanted))
+    }
+
+    /// Requests wanted images and receives loaded ones, true while some are
+    /// loading.
+    pub fn poll(&mut self, store: &Store) -> bool {
+        let mut loading = false;
+        for (url, slot) in self.slots.iter_mut() {
+            match slot {
+                Slot::Wanted => {
+                    let url = url.clone();
+                    *slot = Slot::Loading(store.send(|reply| Command::Image(url, reply)));
+                    loading = true;
+                }
+                Slot::Loading(pending) => match pending.try_recv() {
+                    None => loading = true,
+                    Some(Ok(image)) => {
+                        let size = [image.width() as usize, image.height() as usize];
+                        let image = ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+                        *slot = Slot::Loaded(RetainedImage::from_color_image(url, image));
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!("{url}: {e}");
+                        *slot = Slot::Failed(e.to_string());
+                    }
+                },
+                Slot::Loaded(_) | Slot::Failed(_) => {}
+            }
+        }
+        loading
+    }
+}
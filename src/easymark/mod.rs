@@ -1,6 +1,12 @@
+mod cache;
+pub mod document;
+mod images;
 mod render;
 mod syntax_highlighting;
 
+pub use cache::{Document, Documents};
+pub use document::{build, Block, Image, Inline, Marks};
 pub use html_to_pulldown_cmark_events::parser;
+pub use images::{Images, Slot};
 pub use render::render;
 pub use syntax_highlighting::code_view_ui;
@@ -1,509 +1,172 @@
-use std::slice::Iter;
+use eframe::egui::{self, vec2, Align, Layout, RichText, Stroke, TextStyle, Ui};
 
-use anyhow::Result;
-use eframe::{
-    egui::{self, *},
-    epaint::text::LayoutJob,
-};
-use pulldown_cmark::{CodeBlockKind, Event, Tag};
-use scraper::{Html, Node};
+use super::{code_view_ui, document, Block, Image, Images, Inline, Marks, Slot};
 
-use super::code_view_ui;
-
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
-pub struct Style {
-    /// # heading (large text)
-    pub heading: Option<usize>,
-
-    /// > quoted (slightly dimmer color or other font style)
-    pub quoted: bool,
-
-    /// `code` (monospace, some other color)
-    pub code: bool,
-
-    /// self.strong* (emphasized, e.g. bold)
-    pub strong: bool,
-
-    /// _underline_
-    pub underline: bool,
-
-    /// ~strikethrough~
-    pub strikethrough: bool,
-
-    /// /italics/
-    pub italics: bool,
-
-    /// $small$
-    pub small: bool,
-
-    /// ^raised^
-    pub raised: bool,
-
-    pub link: bool,
-
-    weak: bool,
-
-    pub codeblock: bool,
-
-    pub newline: bool,
-}
-
-fn rich_text_from_style(text: &str, style: &Style, row_height: f32, diff: f32) -> RichText {
-    let Style {
-        heading,
-        quoted,
-        code,
-        strong,
-        underline,
-        strikethrough,
-        italics,
-        small,
-        raised,
-        ..
-    } = *style;
-
-    let small = small || raised; // Raised text is also smaller
-
-    let mut rich_text = RichText::new(text);
-
-    if let Some(level) = heading {
-        match level {
-            1 => {
-                rich_text = rich_text.strong().heading();
-            }
-            k @ 2..=6 => {
-                let size = row_height
-                    + diff
-                        * (match k {
-                            2 => 0.835,
-                            3 => 0.668,
-                            4 => 0.501,
-                            5 => 0.334,
-                            6 => 0.167,
-                            _ => unreachable!(),
-                        });
-                rich_text = rich_text.strong().size(size);
-            }
-            _ => {
-                unreachable!();
-            }
-        }
-    }
-
-    if small && heading.is_none() {
-        rich_text = rich_text.small();
-    }
-    if code {
-        rich_text = rich_text.code();
-    }
-    if strong {
-        rich_text = rich_text.strong();
-    } else if quoted {
-        rich_text = rich_text.weak();
-    }
-    if underline {
-        rich_text = rich_text.underline();
-    }
-    if strikethrough {
-        rich_text = rich_text.strikethrough();
-    }
-    if italics {
-        rich_text = rich_text.italics();
-    }
-    if raised {
-        rich_text = rich_text.raised();
-    }
-    rich_text
+/// Draws the blocks of a document from top to bottom, images are loaded
+/// into `images`.
+pub fn render(ui: &mut Ui, blocks: &[Block], images: &mut Images) {
+    let gap = ui.text_style_height(&TextStyle::Body) / 2.0;
+    blocks_ui(ui, blocks, gap, images);
 }
 
-fn get_text_color(style: &Style, visuals: &Visuals) -> Option<Color32> {
-    // if let Some(text_color) = self.text_color {
-    //     Some(text_color)
-    // } else if style.strong {
-    if style.strong {
-        Some(visuals.strong_text_color())
-    } else if style.weak {
-        Some(visuals.weak_text_color())
-    } else {
-        visuals.override_text_color
+/// `gap` is the space after each block, none in list items.
+fn blocks_ui(ui: &mut Ui, blocks: &[Block], gap: f32, images: &mut Images) {
+    for block in blocks {
+        block_ui(ui, block, images);
+        ui.add_space(gap);
     }
 }
 
-fn text_format(
-    font_id: FontId,
-    style: &Style,
-    estyle: &egui::Style,
-    default_valign: Align,
-) -> TextFormat {
-    let text_color = get_text_color(style, &estyle.visuals);
-
-    let Style {
-        heading,
-        quoted,
-        code,
-        strong,
-        underline,
-        strikethrough,
-        italics,
-        small,
-        raised,
-        ..
-    } = *style;
-
-    let job_has_color = text_color.is_some();
-    let line_color = text_color.unwrap_or_else(|| estyle.visuals.text_color());
-    let text_color = text_color.unwrap_or(Color32::TEMPORARY_COLOR);
-
-    let background_color = if code {
-        estyle.visuals.code_bg_color
-    } else {
-        Color32::default()
-    };
-    let underline = if underline {
-        Stroke::new(1.0, line_color)
-    } else {
-        Stroke::NONE
-    };
-    let strikethrough = if strikethrough {
-        Stroke::new(1.0, line_color)
-    } else {
-        Stroke::NONE
-    };
-
-    let valign = if raised { Align::TOP } else { default_valign };
-
-    TextFormat {
-        font_id,
-        color: text_color,
-        background: background_color,
-        italics,
-        underline,
-        strikethrough,
-        valign,
+fn block_ui(ui: &mut Ui, block: &Block, images: &mut Images) {
+    match block {
+        Block::Paragraph(inlines) => inlines_ui(ui, inlines, None, images),
+        Block::Heading(level, inlines) => inlines_ui(ui, inlines, Some(*level), images),
+        Block::List(start, items) => {
+            for (i, item) in items.iter().enumerate() {
+                ui.with_layout(Layout::left_to_right(Align::TOP), |ui| {
+                    match start {
+                        Some(start) => ui.strong(format!("{}.", start + i as u64)),
+                        None => ui.strong("•"),
+                    };
+                    ui.vertical(|ui| blocks_ui(ui, item, 0.0, images));
+                });
+            }
+        }
+        Block::Quote(blocks) => {
+            let rect = ui
+                .indent("quote", |ui| {
+                    ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+                    blocks_ui(ui, blocks, 0.0, images);
+                })
+                .response
+                .rect;
+            ui.painter().vline(
+                rect.left() + 2.0,
+                rect.y_range(),
+                Stroke::new(2.0, ui.visuals().weak_text_color()),
+            );
+        }
+        Block::Code { lang, text } => code_view_ui(
+            ui,
+            text.trim_end_matches('\n'),
+            lang.as_deref().unwrap_or_default(),
+        ),
+        Block::Table { head, rows } => {
+            egui::Grid::new(ui.auto_id_with("table"))
+                .striped(true)
+                .show(ui, |ui| {
+                    for cell in head {
+                        ui.strong(document::text(cell));
+                    }
+                    if !head.is_empty() {
+                        ui.end_row();
+                    }
+                    for row in rows {
+                        for cell in row {
+                            inlines_ui(ui, cell, None, images);
+                        }
+                        ui.end_row();
+                    }
+                });
+        }
+        Block::Figure(image) => image_ui(ui, image, images),
+        Block::Rule => {
+            ui.separator();
+        }
     }
 }
 
-fn bulleted_point(ui: &mut Ui, width: f32, row_height: f32) -> Rect {
-    let (rect, response) = ui.allocate_exact_size(vec2(width, row_height), Sense::hover());
-    ui.painter().circle_filled(
-        rect.center(),
-        rect.height() / 8.0,
-        ui.visuals().strong_text_color(),
-    );
-    rect
-}
-
-fn numbered_point(ui: &mut Ui, width: f32, number: &str, row_height: f32) -> Rect {
-    let (rect, response) = ui.allocate_exact_size(vec2(width, row_height), Sense::hover());
-    let mut text = String::new();
-    text.push_str(&number.to_string());
-    text.push('.');
-    let text_color = ui.visuals().strong_text_color();
-    ui.painter().text(
-        rect.left_center(),
-        Align2::LEFT_CENTER,
-        text,
-        TextStyle::Body.resolve(ui.style()),
-        text_color,
-    )
-}
-
-fn new_line(ui: &mut Ui, row_height: f32) {
-    ui.allocate_exact_size(vec2(0.0, row_height), Sense::hover()); // make sure we take up some height
-    ui.end_row();
-    ui.set_row_height(row_height);
-}
-
-pub fn render(ui: &mut Ui, events: Vec<Event<'_>>) -> Result<()> {
-    let initial_size = vec2(ui.available_width(), ui.spacing().interact_size.y);
-    let layout = Layout::left_to_right(Align::BOTTOM).with_main_wrap(true);
-
-    ui.allocate_ui_with_layout(initial_size, layout, |ui| {
+/// Draws inlines wrapped into lines, `heading` is the level of a heading.
+fn inlines_ui(ui: &mut Ui, inlines: &[Inline], heading: Option<u8>, images: &mut Images) {
+    ui.horizontal_wrapped(|ui| {
         ui.spacing_mut().item_spacing.x = 0.0;
-        let max_height = ui.text_style_height(&TextStyle::Heading);
-        let row_height = ui.text_style_height(&TextStyle::Body);
-        let one_indent = row_height / 2.0;
-        let diff = max_height - row_height;
-
-        ui.set_row_height(row_height);
-
-        let mut style = Style::default();
-        // None: bulleted, Some(n): numbered
-        let mut list = None;
-        let mut rich_text = None;
-        let mut lang = None;
-        let mut iter = events.iter();
-
-        render_by_events(
-            ui,
-            &mut iter,
-            &mut style,
-            &mut list,
-            &mut rich_text,
-            &mut lang,
-            row_height,
-            diff,
-            one_indent,
-        );
+        for inline in inlines {
+            inline_ui(ui, inline, heading, images);
+        }
     });
-
-    Ok(())
 }
 
-fn render_by_events(
-    ui: &mut Ui,
-    iter: &mut Iter<Event<'_>>,
-    style: &mut Style,
-    list: &mut Option<Vec<Option<u64>>>,
-    rich_text: &mut Option<RichText>,
-    lang: &mut Option<String>,
-    row_height: f32,
-    diff: f32,
-    one_indent: f32,
-) {
-    while let Some(event) = iter.next() {
-        match &event {
-            Event::Start(tag) => {
-                match tag {
-                    // inline
-                    Tag::Strong => {
-                        style.strong = true;
-                    }
-                    Tag::Emphasis => {
-                        style.italics = true;
-                    }
-                    Tag::Strikethrough => {
-                        style.strikethrough = true;
-                    }
-                    Tag::Link(_, href, _) => {
-                        style.link = true;
-                        ui.horizontal_centered(|ui| {
-                            let mut job = LayoutJob::default();
-
-                            while let Some(event) = iter.next() {
-                                match event {
-                                    // inline start
-                                    Event::Start(tag) => match tag {
-                                        Tag::Strong => {
-                                            style.strong = true;
-                                        }
-                                        Tag::Emphasis => {
-                                            style.italics = true;
-                                        }
-                                        Tag::Strikethrough => {
-                                            style.strikethrough = true;
-                                        }
-                                        _ => {
-                                            unreachable!();
-                                        }
-                                    },
-                                    // inline end
-                                    Event::End(tag) => match tag {
-                                        Tag::Strong => {
-                                            style.strong = false;
-                                        }
-                                        Tag::Emphasis => {
-                                            style.italics = false;
-                                        }
-                                        Tag::Strikethrough => {
-                                            style.strikethrough = false;
-                                        }
-                                        Tag::Link(..) => {
-                                            break;
-                                        }
-                                        _ => {
-                                            unreachable!();
-                                        }
-                                    },
-                                    Event::Text(text) => job.append(
-                                        text,
-                                        0.0,
-                                        text_format(
-                                            TextStyle::Body.resolve(ui.style()),
-                                            style,
-                                            ui.style(),
-                                            ui.layout().vertical_align(),
-                                        ),
-                                    ),
-                                    Event::Code(text) => {
-                                        style.code = true;
-                                        job.append(
-                                            text,
-                                            0.0,
-                                            text_format(
-                                                TextStyle::Monospace.resolve(ui.style()),
-                                                style,
-                                                ui.style(),
-                                                ui.layout().vertical_align(),
-                                            ),
-                                        );
-                                        style.code = false;
-                                    }
-                                    _ => {
-                                        unreachable!();
-                                    }
-                                }
-                            }
-
-                            ui.hyperlink_to(job, href.to_string());
-                        });
-                        style.link = false;
-                    }
-
-                    // block
-                    Tag::Heading(level, ..) => {
-                        style.heading.replace(*level as usize);
-                    }
-                    Tag::BlockQuote => {
-                        style.quoted = true;
-                        ui.vertical(|ui| {
-                            ui.indent("blockquote", |ui| {
-                                ui.horizontal_wrapped(|ui| {
-                                    render_by_events(
-                                        ui, iter, style, list, rich_text, lang, row_height, diff,
-                                        one_indent,
-                                    );
-                                });
-                            });
-                        });
-                        style.quoted = false;
-                        new_line(ui, row_height);
-                    }
-                    Tag::List(n) => {
-                        let list = list.get_or_insert_with(Vec::new);
-                        style.newline = !list.is_empty();
-                        list.push(*n);
-                    }
-                    Tag::Item => {
-                        let list = list.get_or_insert_with(Vec::new);
-                        let indents = list.len();
-                        let kind = list.last_mut();
-
-                        if style.newline {
-                            new_line(ui, row_height);
-                        }
-
-                        let width = 3.0 * one_indent * indents.saturating_sub(1) as f32;
-
-                        ui.allocate_exact_size(vec2(width, row_height), Sense::hover());
-                        let rect = if let Some(Some(number)) = kind {
-                            let rect =
-                                numbered_point(ui, one_indent, &number.to_string(), row_height);
-                            *number += 1;
-                            rect
-                        } else {
-                            bulleted_point(ui, one_indent, row_height)
-                        };
-                        ui.allocate_exact_size(vec2(rect.width(), row_height), Sense::hover());
-                    }
-                    Tag::CodeBlock(kind) => {
-                        style.codeblock = true;
-                        match kind {
-                            CodeBlockKind::Indented => *lang = None,
-                            CodeBlockKind::Fenced(text) => {
-                                lang.replace(text.to_string());
-                            }
-                        }
-                    }
-
-                    // TODO: download image
-                    Tag::Image(..) => {}
-
-                    k @ _ => {
-                        tracing::trace!("{:?}", k);
-                    }
-                }
-            }
-            Event::End(tag) => {
-                match tag {
-                    // inline
-                    Tag::Strong => {
-                        style.strong = false;
-                    }
-                    Tag::Emphasis => {
-                        style.italics = false;
-                    }
-                    Tag::Strikethrough => {
-                        style.strikethrough = false;
-                    }
-
-                    // block
-                    Tag::Heading(..) => {
-                        style.heading = None;
-                        new_line(ui, row_height);
-                    }
-                    Tag::List(..) => {
-                        let list = list.get_or_insert_with(Vec::new);
-                        list.pop();
-                        if list.is_empty() {
-                            new_line(ui, row_height);
-                        }
-                        style.newline = false;
-                    }
-                    Tag::BlockQuote => {
-                        break;
-                    }
-                    Tag::Paragraph => {
-                        new_line(ui, row_height);
-                    }
-                    Tag::Image(..) => {
-                        new_line(ui, row_height);
-                    }
-                    Tag::Item => {
-                        style.newline = true;
-                    }
-                    Tag::CodeBlock(..) => {
-                        style.codeblock = false;
-                        new_line(ui, row_height);
-                    }
-
-                    k @ _ => {
-                        tracing::trace!("{:?}", k);
-                    }
-                }
-            }
-
-            // remove `\n`
-            Event::Text(text) => {
-                let mut text = text.to_string();
-                if style.codeblock {
-                    code_view_ui(ui, &text, &lang.take().unwrap_or_default());
-                    style.codeblock = false;
-                    continue;
-                }
-                text = text.trim_matches('\n').to_string();
-                let rt = rich_text_from_style(&text, &style, row_height, diff);
-                ui.label(rt);
-            }
-
-            // Inline code
-            Event::Code(code) => {
-                style.code = true;
-                let mut text = code.to_string();
-                if style.quoted {
-                    text = text.trim_matches('\n').to_string();
-                }
-                if let Some(index) = text.find('\n') {
-                    text = text[..index].to_string();
-                }
-                let rt = rich_text_from_style(&text, &style, row_height, diff);
-                ui.label(rt);
-                style.code = false;
+fn inline_ui(ui: &mut Ui, inline: &Inline, heading: Option<u8>, images: &mut Images) {
+    match inline {
+        Inline::Text(text, marks) => {
+            ui.label(rich_text(ui, text, *marks, heading));
+        }
+        Inline::Code(code) => {
+            ui.label(RichText::new(code).code());
+        }
+        Inline::Link {
+            href,
+            title,
+            content,
+        } => {
+            let marks = content
+                .iter()
+                .find_map(|inline| match inline {
+                    Inline::Text(_, marks) => Some(*marks),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let text = document::text(content);
+            let text = match text.trim() {
+                "" => href.as_str(),
+                text => text,
+            };
+            let text = match content.as_slice() {
+                [Inline::Image(_)] => format!("🖼 {text}"),
+                _ => text.to_owned(),
+            };
+            let resp = ui.hyperlink_to(rich_text(ui, &text, marks, heading), href);
+            if !title.is_empty() {
+                resp.on_hover_text(title);
             }
+        }
+        Inline::Image(image) => image_ui(ui, image, images),
+        Inline::Break => ui.end_row(),
+    }
+}
 
-            Event::SoftBreak => {
-                ui.label(" ");
-            }
-            Event::HardBreak => {
-                new_line(ui, row_height);
-            }
-            Event::Rule => {
-                ui.add(Separator::default().horizontal());
+/// An image scaled down to the width of the reader, its alt text while it
+/// loads or when it fails.
+fn image_ui(ui: &mut Ui, image: &Image, images: &mut Images) {
+    let alt = match image.alt.as_str() {
+        "" => "image",
+        alt => alt,
+    };
+    let placeholder = RichText::new(format!("🖼 {alt}")).weak();
+    match images.get(&image.url) {
+        Some(Slot::Loaded(texture)) => {
+            let resp = texture.show_max_size(ui, vec2(ui.available_width(), f32::INFINITY));
+            if !image.alt.is_empty() {
+                resp.on_hover_text(&image.alt);
             }
+        }
+        Some(Slot::Failed(e)) => {
+            ui.label(placeholder)
+                .on_hover_text(format!("{}\n{e}", image.url));
+        }
+        Some(Slot::Wanted | Slot::Loading(_)) | None => {
+            ui.label(placeholder).on_hover_text(&image.url);
+        }
+    }
+}
 
-            k @ _ => {
-                tracing::trace!("{:?}", k);
-            }
-        };
+fn rich_text(ui: &Ui, text: &str, marks: Marks, heading: Option<u8>) -> RichText {
+    let mut rich_text = RichText::new(text);
+    if let Some(level) = heading {
+        let body = TextStyle::Body.resolve(ui.style()).size;
+        let largest = TextStyle::Heading.resolve(ui.style()).size;
+        // the size of a heading at level 1, down to the body at level 7
+        let size = body + (largest - body) * f32::from(7 - level.clamp(1, 6)) / 6.0;
+        rich_text = rich_text.size(size).strong();
+    }
+    if marks.strong {
+        rich_text = rich_text.strong();
+    }
+    if marks.italics {
+        rich_text = rich_text.italics();
+    }
+    if marks.strikethrough {
+        rich_text = rich_text.strikethrough();
     }
+    rich_text
 }
@@ -0,0 +1,220 @@
+//! Errors of the worker commands, one enum per kind of operation.
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use rusqlite::ErrorCode;
+use tokio::task::JoinError;
+
+/// The worker dropped a command without answering it.
+#[derive(Debug, thiserror::Error)]
+#[error("worker is gone")]
+pub struct Gone;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FeedError {
+    #[error("URL already subscribed")]
+    AlreadySubscribed,
+    /// the url or a url redirecting to it
+    #[error("already subscribed as '{0}'")]
+    SubscribedAs(String),
+    /// the url is not a feed
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error("database error: {0}")]
+    Database(anyhow::Error),
+    #[error("aborted: {0}")]
+    Aborted(#[from] JoinError),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FolderError {
+    #[error("folder already exists")]
+    AlreadyExists,
+    #[error("database error: {0}")]
+    Database(anyhow::Error),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("{0}")]
+    Status(StatusCode),
+    /// the server asked to come back later than a retry can wait
+    #[error("{0}, retry after {}s", .1.as_secs())]
+    RetryAfter(StatusCode, Duration),
+    #[error("too many redirects")]
+    TooManyRedirects,
+    #[error("invalid feed: {0}")]
+    Parse(#[from] feed_rs::parser::ParseFeedError),
+    #[error("database error: {0}")]
+    Database(anyhow::Error),
+    #[error("fetch aborted: {0}")]
+    Aborted(#[from] JoinError),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("can't read file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid OPML: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("not an OPML document")]
+    NotOpml,
+    #[error("database error: {0}")]
+    Database(anyhow::Error),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("can't write file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("can't write OPML: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("database error: {0}")]
+    Database(anyhow::Error),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RuleError {
+    #[error("rule already exists")]
+    AlreadyExists,
+    #[error("a rule needs a condition and an action")]
+    Incomplete,
+    #[error("database error: {0}")]
+    Database(anyhow::Error),
+    #[error("dry run aborted: {0}")]
+    Aborted(#[from] JoinError),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageError {
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error("image is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("can't decode image: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("image aborted: {0}")]
+    Aborted(#[from] JoinError),
+    #[error(transparent)]
+    Gone(#[from] Gone),
+}
+
+impl FetchError {
+    /// HTTP status of a failed response.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Status(status) | Self::RetryAfter(status, _) => Some(status.as_u16()),
+            Self::Request(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+/// Unique constraints are the only failures a user can fix.
+fn is_unique_violation(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<rusqlite::Error>(),
+        Some(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::ConstraintViolation
+    )
+}
+
+impl From<anyhow::Error> for FeedError {
+    fn from(e: anyhow::Error) -> Self {
+        if is_unique_violation(&e) {
+            Self::AlreadySubscribed
+        } else {
+            Self::Database(e)
+        }
+    }
+}
+
+impl From<anyhow::Error> for FolderError {
+    fn from(e: anyhow::Error) -> Self {
+        if is_unique_violation(&e) {
+            Self::AlreadyExists
+        } else {
+            Self::Database(e)
+        }
+    }
+}
+
+impl From<anyhow::Error> for FetchError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<anyhow::Error> for ImportError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<anyhow::Error> for ExportError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<anyhow::Error> for RuleError {
+    fn from(e: anyhow::Error) -> Self {
+        if is_unique_violation(&e) {
+            Self::AlreadyExists
+        } else {
+            Self::Database(e)
+        }
+    }
+}
+
+impl From<r2d2::Error> for FeedError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Database(e.into())
+    }
+}
+
+impl From<r2d2::Error> for FolderError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Database(e.into())
+    }
+}
+
+impl From<r2d2::Error> for FetchError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Database(e.into())
+    }
+}
+
+impl From<r2d2::Error> for ImportError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Database(e.into())
+    }
+}
+
+impl From<r2d2::Error> for ExportError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Database(e.into())
+    }
+}
+
+impl From<r2d2::Error> for RuleError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Database(e.into())
+    }
+}
@@ -0,0 +1,108 @@
+//! Images of articles kept on disk, keyed by a hash of their url.
+
+use std::{fs, io, path::PathBuf, time::SystemTime};
+
+use image::RgbaImage;
+
+/// bytes of an image, larger ones are not fetched
+pub const MAX_IMAGE_SIZE: u64 = 10 * 1024 * 1024;
+/// bytes of all cached images
+const DEFAULT_CAPACITY: u64 = 256 * 1024 * 1024;
+/// pixels of the longer side, larger images are scaled down to fit in a
+/// texture
+const MAX_SIDE: u32 = 2048;
+
+/// Fetched images, the least recently used ones are removed once they take
+/// more than the capacity.
+#[derive(Clone, Debug)]
+pub struct ImageCache {
+    dir: PathBuf,
+    /// bytes
+    capacity: u64,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self::with_capacity(dir, DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(dir: PathBuf, capacity: u64) -> Self {
+        Self { dir, capacity }
+    }
+
+    /// The bytes of a cached image, it becomes the most recently used one.
+    pub fn get(&self, url: &str) -> Option<Vec<u8>> {
+        let path = self.path(url);
+        let bytes = fs::read(&path).ok()?;
+        // files are removed by their modification time
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(bytes)
+    }
+
+    /// Keeps an image, then removes the least recently used ones above the
+    /// capacity.
+    pub fn insert(&self, url: &str, bytes: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(url), bytes)?;
+        self.evict()
+    }
+
+    /// Bytes of all cached images.
+    pub fn size(&self) -> io::Result<u64> {
+        Ok(self.files()?.iter().map(|(_, len, _)| len).sum())
+    }
+
+    fn evict(&self) -> io::Result<()> {
+        let mut files = self.files()?;
+        let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+        // the oldest first
+        files.sort();
+        for (_, len, path) in files {
+            if size <= self.capacity {
+                break;
+            }
+            fs::remove_file(path)?;
+            size -= len;
+        }
+        Ok(())
+    }
+
+    fn files(&self) -> io::Result<Vec<(SystemTime, u64, PathBuf)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .collect())
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(url.as_bytes())))
+    }
+}
+
+/// 64-bit FNV-1a, names of cached files stay the same across builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Decodes an image, larger ones are scaled down.
+pub fn decode(bytes: &[u8]) -> Result<RgbaImage, image::ImageError> {
+    let image = image::load_from_memory(bytes)?;
+    let image = if image.width() > MAX_SIDE || image.height() > MAX_SIDE {
+        image.thumbnail(MAX_SIDE, MAX_SIDE)
+    } else {
+        image
+    };
+    Ok(image.to_rgba8())
+}
@@ -2,12 +2,22 @@ mod components;
 
 // use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use tokio::sync::watch::Sender;
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+
+use errors::Gone;
+use service::{Command, Pending, Reply};
 
 pub use components::*;
 pub mod db;
+pub mod discovery;
 pub mod easymark;
+pub mod errors;
+pub mod images;
 pub mod models;
+pub mod opml;
+pub mod rules;
+pub mod search;
+pub mod service;
 pub mod ui;
 pub mod utils;
 pub mod windows;
@@ -27,21 +37,52 @@ pub enum Message {
     RefreshFolders,
     Feed(Action, models::Feed),
     Folder(Action, models::Folder),
+    SmartFolder(Action, models::SmartFolder),
+    /// dropped onto the window
+    File(std::path::PathBuf),
 }
 
 #[derive(Debug)]
 pub struct Store {
-    pub sender: Sender<Message>,
+    pub sender: UnboundedSender<Command>,
     pub folders: Arc<RwLock<Vec<models::Folder>>>,
+    pub smart_folders: Arc<RwLock<Vec<models::SmartFolder>>>,
+    pub tags: Arc<RwLock<Vec<models::Tag>>>,
+    pub progress: Arc<RwLock<models::Progress>>,
+    /// pushed by the worker, drained by the UI
+    pub notifications: Arc<RwLock<Vec<models::Notification>>>,
     // pub feeds: Arc<RwLock<HashMap<u64, Vec<models::Feed>>>>,
 }
 
 impl Store {
-    pub fn new(sender: Sender<Message>, folders: Arc<RwLock<Vec<models::Folder>>>) -> Self {
+    pub fn new(
+        sender: UnboundedSender<Command>,
+        folders: Arc<RwLock<Vec<models::Folder>>>,
+        smart_folders: Arc<RwLock<Vec<models::SmartFolder>>>,
+        tags: Arc<RwLock<Vec<models::Tag>>>,
+        progress: Arc<RwLock<models::Progress>>,
+        notifications: Arc<RwLock<Vec<models::Notification>>>,
+    ) -> Self {
         Self {
             sender,
             folders,
+            smart_folders,
+            tags,
+            progress,
+            notifications,
             // feeds: Arc::default(),
         }
     }
+
+    /// Queues a command, the returned outcome resolves when the worker answers.
+    pub fn send<T, E: From<Gone>>(
+        &self,
+        command: impl FnOnce(Reply<T, E>) -> Command,
+    ) -> Pending<T, E> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(e) = self.sender.send(command(tx)) {
+            tracing::error!("{e}");
+        }
+        Pending::new(rx)
+    }
 }
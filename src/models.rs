@@ -16,13 +16,137 @@ pub struct Feed {
     pub name: String,
     pub url: String,
     pub site: Option<String>,
+    /// `Atom`, `JSON`, `RSS0`, `RSS1` or `RSS2`, known once fetched
+    #[serde(default)]
+    pub kind: Option<String>,
     pub last_seen: i64,
     pub folder_id: u64,
-    /// true: loading, false not loading
+    /// `ETag` of the latest response, sent back as `If-None-Match`
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// `Last-Modified` of the latest response, sent back as `If-Modified-Since`
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// HTTP status of the latest response
+    #[serde(default)]
+    pub http_status: Option<u16>,
+    /// refresh interval in seconds, `None` follows the global default
+    #[serde(default)]
+    pub interval: Option<u64>,
+    /// unix timestamp in milliseconds of the latest successful fetch
+    #[serde(default)]
+    pub last_fetched: Option<i64>,
+    /// why the latest fetch failed, cleared by a successful one
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// consecutive failed fetches
+    #[serde(default)]
+    pub failures: u32,
+    /// too many failures, no longer refreshed until a fetch succeeds
     #[serde(default)]
-    pub status: bool,
+    pub dead: bool,
+    /// url before the latest permanent redirect
     #[serde(default)]
-    pub articles: Option<Vec<Article>>,
+    pub moved_from: Option<String>,
+    /// categories of new articles become tags
+    #[serde(default)]
+    pub import_categories: bool,
+    /// days articles are kept, `None` follows the global default and `0`
+    /// keeps them all
+    #[serde(default)]
+    pub keep_days: Option<u32>,
+    /// latest articles kept, `None` follows the global default and `0` keeps
+    /// them all
+    #[serde(default)]
+    pub keep_articles: Option<u32>,
+    /// the server asked not to be refreshed before, unix timestamp in
+    /// milliseconds, skipped by the scheduler until then
+    #[serde(default)]
+    pub retry_at: Option<i64>,
+    #[serde(default)]
+    pub unread: usize,
+    #[serde(default)]
+    pub state: FetchState,
+}
+
+/// The settings of a feed edited by the user, the rest of a feed is kept by
+/// the worker.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct FeedSettings {
+    pub id: u64,
+    pub name: String,
+    pub url: String,
+    pub folder_id: u64,
+    #[serde(default)]
+    pub interval: Option<u64>,
+    #[serde(default)]
+    pub import_categories: bool,
+    #[serde(default)]
+    pub keep_days: Option<u32>,
+    #[serde(default)]
+    pub keep_articles: Option<u32>,
+}
+
+impl FeedSettings {
+    /// Copies the settings onto a feed, its cache validators belong to the
+    /// previous url when it changed.
+    pub fn apply(&self, feed: &mut Feed) {
+        if feed.url != self.url {
+            feed.url = self.url.clone();
+            feed.etag = None;
+            feed.last_modified = None;
+        }
+        feed.id = self.id;
+        feed.name = self.name.clone();
+        feed.folder_id = self.folder_id;
+        feed.interval = self.interval;
+        feed.import_categories = self.import_categories;
+        feed.keep_days = self.keep_days;
+        feed.keep_articles = self.keep_articles;
+    }
+}
+
+impl From<&Feed> for FeedSettings {
+    fn from(feed: &Feed) -> Self {
+        Self {
+            id: feed.id,
+            name: feed.name.clone(),
+            url: feed.url.clone(),
+            folder_id: feed.folder_id,
+            interval: feed.interval,
+            import_categories: feed.import_categories,
+            keep_days: feed.keep_days,
+            keep_articles: feed.keep_articles,
+        }
+    }
+}
+
+/// Where a feed is in its fetch, only kept in memory.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub enum FetchState {
+    #[default]
+    Idle,
+    /// waiting for a free request slot
+    Queued,
+    Fetching,
+    Parsing,
+    Storing,
+    Failed {
+        reason: String,
+        /// unix timestamp in milliseconds
+        at: i64,
+    },
+    NotModified,
+}
+
+impl FetchState {
+    /// A fetch is queued or running.
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self,
+            Self::Queued | Self::Fetching | Self::Parsing | Self::Storing
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
@@ -42,15 +166,219 @@ pub struct Article {
     pub created: i64,
     pub updated: i64,
     #[serde(default)]
+    pub read: bool,
+    /// unix timestamp in milliseconds
+    #[serde(default)]
+    pub read_at: Option<i64>,
+    /// when it was starred, unix timestamp in milliseconds
+    #[serde(default)]
+    pub starred: Option<i64>,
+    #[serde(default)]
     pub authors: Option<Vec<Author>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-impl Article {
-    pub fn clone_with_content_authors(&self) -> Self {
+/// An article in a list, its content is loaded once it is opened.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct ArticleSummary {
+    pub id: u64,
+    pub feed_id: u64,
+    pub url: String,
+    pub title: String,
+    /// published
+    pub created: i64,
+    pub updated: i64,
+    #[serde(default)]
+    pub read: bool,
+    /// when it was starred, unix timestamp in milliseconds
+    #[serde(default)]
+    pub starred: Option<i64>,
+}
+
+impl ArticleSummary {
+    /// Where the page after this article starts in `timeline`.
+    pub fn cursor(&self, timeline: Timeline) -> Cursor {
+        Cursor {
+            key: match timeline {
+                Timeline::Starred => self.starred.unwrap_or_default(),
+                _ => self.created.max(self.updated),
+            },
+            id: self.id,
+        }
+    }
+}
+
+/// Position in a list of articles, a page starts after the sort key and id
+/// of the last article of the previous one.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    /// star time of starred articles, the latest of published and updated
+    /// otherwise
+    pub key: i64,
+    pub id: u64,
+}
+
+/// What the article list shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timeline {
+    /// articles of a feed
+    Feed(u64),
+    /// articles of all feeds, the latest first
+    All,
+    Unread,
+    /// published or updated since midnight
+    Today,
+    /// articles of all feeds in a folder
+    Folder(u64),
+    /// starred articles of all feeds
+    Starred,
+    /// results of the search box
+    Search,
+    /// articles matching a smart folder
+    Smart(u64),
+    /// articles with a tag
+    Tag(u64),
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::Feed(0)
+    }
+}
+
+/// A tag of articles.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub id: u64,
+    pub name: String,
+    /// unread articles with the tag
+    #[serde(default)]
+    pub unread: usize,
+}
+
+/// A saved filter, shown as a folder.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct SmartFolder {
+    pub id: u64,
+    pub name: String,
+    pub filter: SmartFilter,
+    /// matching unread articles
+    #[serde(default)]
+    pub unread: usize,
+}
+
+/// Articles of a smart folder match every set field. Feeds and folders
+/// together are the feeds to match.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct SmartFilter {
+    /// words, "phrases" and prefix* of the search index
+    pub keywords: String,
+    pub feeds: Vec<u64>,
+    pub folders: Vec<u64>,
+    /// any of these authors, ignoring case
+    pub authors: Vec<String>,
+    pub read: Option<bool>,
+    pub starred: Option<bool>,
+    /// published or updated since, unix timestamp in milliseconds
+    pub since: Option<i64>,
+    /// published or updated before, unix timestamp in milliseconds
+    pub until: Option<i64>,
+}
+
+/// Articles a command applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Article(u64),
+    Feed(u64),
+    Folder(u64),
+    All,
+}
+
+/// A parsed feed which is not subscribed yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedPreview {
+    /// after redirects
+    pub url: String,
+    pub title: Option<String>,
+    pub feed_type: FeedType,
+    pub description: Option<String>,
+    pub entries: usize,
+    /// titles of the latest entries, newest first
+    pub latest: Vec<String>,
+}
+
+/// Articles kept by default, starred and unread ones are always kept. `0`
+/// keeps them all.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Retention {
+    pub keep_days: u32,
+    pub keep_articles: u32,
+}
+
+/// Outcome of a prune.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Pruned {
+    pub articles: usize,
+    /// bytes given back to the file system
+    pub freed: u64,
+}
+
+/// Aggregate progress of in-flight fetches.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+}
+
+impl Progress {
+    pub fn is_active(&self) -> bool {
+        self.done < self.total
+    }
+
+    /// A fetch is queued, a new round starts when the previous one is finished.
+    pub fn start(&mut self) {
+        if !self.is_active() {
+            *self = Self::default();
+        }
+        self.total += 1;
+    }
+
+    pub fn finish(&mut self, ok: bool) {
+        self.done += 1;
+        if !ok {
+            self.failed += 1;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+/// A message for the notifications area.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub level: Level,
+    pub text: String,
+}
+
+impl Notification {
+    pub fn info(text: impl Into<String>) -> Self {
         Self {
-            content: String::new(),
-            authors: None,
-            ..self.clone()
+            level: Level::Info,
+            text: text.into(),
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self {
+            level: Level::Error,
+            text: text.into(),
         }
     }
 }
@@ -64,21 +392,33 @@ impl Feed {
             folder_id,
             last_seen: 0,
             site: None,
-            status: false,
-            articles: None,
+            kind: None,
+            etag: None,
+            last_modified: None,
+            http_status: None,
+            interval: None,
+            last_fetched: None,
+            last_error: None,
+            failures: 0,
+            dead: false,
+            moved_from: None,
+            import_categories: false,
+            keep_days: None,
+            keep_articles: None,
+            retry_at: None,
+            unread: 0,
+            state: FetchState::Idle,
         }
     }
 
-    pub fn clone_with_last_article(&self) -> Self {
-        Self {
-            articles: self
-                .articles
-                .as_ref()
-                .and_then(|articles| articles.last().map(|a| a.clone_with_content_authors()))
-                .or_else(|| Some(Article::default()))
-                .map(|a| vec![a]),
-            ..self.clone()
-        }
+    /// Not refreshed for a day, or for three intervals when it is refreshed
+    /// less often, `now` in milliseconds.
+    pub fn is_stale(&self, now: i64) -> bool {
+        let after = self
+            .interval
+            .map_or(0, |i| i as i64 * 3 * 1000)
+            .max(24 * 60 * 60 * 1000);
+        matches!(self.last_fetched, Some(t) if now - t > after)
     }
 }
 
@@ -89,4 +429,9 @@ impl Folder {
             ..self.clone()
         }
     }
+
+    /// Unread articles of all feeds.
+    pub fn unread(&self) -> usize {
+        self.feeds.iter().flatten().map(|f| f.unread).sum()
+    }
 }
@@ -0,0 +1,218 @@
+//! OPML import and export of subscriptions, `<outline>` groups are folders.
+
+use std::fmt;
+
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+use url::Url;
+
+use crate::{
+    errors::{ExportError, ImportError},
+    models::{Feed, Folder},
+};
+
+/// Feeds of this folder are written outside of any group, and feeds outside
+/// of any group are imported into it.
+const DEFAULT_FOLDER_ID: u64 = 1;
+
+/// Subscriptions of an OPML document.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Opml {
+    /// groups in document order, feeds outside of any group are in the one
+    /// without a name
+    pub folders: Vec<Folder>,
+    /// `xmlUrl`s which are not http(s) urls
+    pub invalid: Vec<String>,
+}
+
+impl Opml {
+    fn push(&mut self, folder: &str, feed: Feed) {
+        match self.folders.iter_mut().find(|f| f.name == folder) {
+            Some(f) => f.feeds.get_or_insert_with(Vec::new).push(feed),
+            None => self.folders.push(Folder {
+                name: folder.to_owned(),
+                feeds: Some(vec![feed]),
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added: usize,
+    /// already subscribed, or listed twice
+    pub skipped: usize,
+    pub invalid: usize,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} skipped, {} invalid",
+            self.added, self.skipped, self.invalid
+        )
+    }
+}
+
+/// Parses OPML 1.0 and 2.0, nested groups are flattened into the nearest one.
+pub fn parse(xml: &str) -> Result<Opml, ImportError> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut opml = Opml::default();
+    let mut root = false;
+    // names of the open outlines, `None` for feeds
+    let mut groups = Vec::<Option<String>>::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if !root => {
+                if !e.local_name().as_ref().eq_ignore_ascii_case(b"opml") {
+                    return Err(ImportError::NotOpml);
+                }
+                root = true;
+            }
+            Event::Start(e) if is_outline(&e) => {
+                let group = outline(&mut opml, &groups, &reader, &e)?;
+                groups.push(group);
+            }
+            Event::Empty(e) if is_outline(&e) => {
+                outline(&mut opml, &groups, &reader, &e)?;
+            }
+            Event::End(e) if e.local_name().as_ref().eq_ignore_ascii_case(b"outline") => {
+                groups.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !root {
+        return Err(ImportError::NotOpml);
+    }
+    Ok(opml)
+}
+
+fn is_outline(e: &BytesStart) -> bool {
+    e.local_name().as_ref().eq_ignore_ascii_case(b"outline")
+}
+
+/// Keeps the feed of an outline, returns its name when it is a group.
+fn outline(
+    opml: &mut Opml,
+    groups: &[Option<String>],
+    reader: &Reader<&[u8]>,
+    e: &BytesStart,
+) -> Result<Option<String>, ImportError> {
+    let title = attribute(reader, e, "title")?
+        .or(attribute(reader, e, "text")?)
+        .map(|t| t.trim().to_owned())
+        .filter(|t| !t.is_empty());
+    let Some(href) = attribute(reader, e, "xmlUrl")? else {
+        return Ok(Some(title.unwrap_or_default()));
+    };
+
+    let href = href.trim();
+    match Url::parse(href) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            let folder = groups
+                .iter()
+                .rev()
+                .flatten()
+                .find(|name| !name.is_empty())
+                .map(String::as_str)
+                .unwrap_or_default();
+            let name = title.unwrap_or_else(|| url.to_string());
+            opml.push(folder, Feed::new(href.to_owned(), name, 0));
+        }
+        _ => opml.invalid.push(href.to_owned()),
+    }
+    Ok(None)
+}
+
+/// Attribute names are matched ignoring case, exporters disagree on `xmlUrl`.
+fn attribute(
+    reader: &Reader<&[u8]>,
+    e: &BytesStart,
+    name: &str,
+) -> Result<Option<String>, ImportError> {
+    for attr in e.attributes() {
+        let attr = attr.map_err(quick_xml::Error::from)?;
+        if attr
+            .key
+            .local_name()
+            .as_ref()
+            .eq_ignore_ascii_case(name.as_bytes())
+        {
+            return Ok(Some(attr.decode_and_unescape_value(reader)?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Writes an OPML 2.0 document, the feeds of the default folder are top level
+/// outlines and every other folder is a group, empty ones included.
+pub fn export(folders: &[Folder]) -> Result<String, ExportError> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("opml").with_attributes([("version", "2.0")]),
+    ))?;
+
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer
+        .create_element("title")
+        .write_text_content(BytesText::new("PinDash News Subscriptions"))?;
+    writer
+        .create_element("dateCreated")
+        .write_text_content(BytesText::new(&chrono::Utc::now().to_rfc2822()))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    let (default, others): (Vec<_>, Vec<_>) =
+        folders.iter().partition(|f| f.id == DEFAULT_FOLDER_ID);
+    for feed in default.iter().filter_map(|f| f.feeds.as_ref()).flatten() {
+        feed_outline(&mut writer, feed)?;
+    }
+    for folder in others {
+        let group = BytesStart::new("outline")
+            .with_attributes([("text", folder.name.as_str()), ("title", &folder.name)]);
+        match folder.feeds.as_deref() {
+            Some(feeds) if !feeds.is_empty() => {
+                writer.write_event(Event::Start(group))?;
+                for feed in feeds {
+                    feed_outline(&mut writer, feed)?;
+                }
+                writer.write_event(Event::End(BytesEnd::new("outline")))?;
+            }
+            _ => writer.write_event(Event::Empty(group))?,
+        }
+    }
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("opml")))?;
+
+    // names and urls are strings, so is the document
+    Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+}
+
+fn feed_outline(writer: &mut Writer<Vec<u8>>, feed: &Feed) -> Result<(), ExportError> {
+    // readers expect "rss" for any kind of feed, atom and json are hints
+    let kind = match feed.kind.as_deref() {
+        Some("Atom") => "atom",
+        Some("JSON") => "json",
+        _ => "rss",
+    };
+    let mut outline = BytesStart::new("outline").with_attributes([
+        ("type", kind),
+        ("text", &feed.name),
+        ("title", &feed.name),
+        ("xmlUrl", &feed.url),
+    ]);
+    if let Some(site) = feed.site.as_deref().filter(|s| !s.is_empty()) {
+        outline.push_attribute(("htmlUrl", site));
+    }
+    writer.write_event(Event::Empty(outline))?;
+    Ok(())
+}
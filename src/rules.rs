@@ -0,0 +1,188 @@
+//! Rules run on new articles of a fetch, each one matching every condition
+//! applies its actions.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{models::Article, utils};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub id: u64,
+    pub name: String,
+    pub enabled: bool,
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            enabled: true,
+            conditions: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+}
+
+/// Text conditions match a part of the text ignoring case.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "field", content = "value", rename_all = "snake_case")]
+pub enum Condition {
+    Title(String),
+    /// the text of the content, without html
+    Content(String),
+    Author(String),
+    Url(String),
+    Category(String),
+    /// the feed of the article
+    Feed(u64),
+}
+
+impl Condition {
+    pub const KINDS: [&'static str; 6] = ["Title", "Content", "Author", "URL", "Category", "Feed"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Title(_) => "Title",
+            Self::Content(_) => "Content",
+            Self::Author(_) => "Author",
+            Self::Url(_) => "URL",
+            Self::Category(_) => "Category",
+            Self::Feed(_) => "Feed",
+        }
+    }
+
+    /// An empty condition of a kind of [`Self::KINDS`].
+    pub fn of_kind(kind: &str) -> Self {
+        match kind {
+            "Content" => Self::Content(String::new()),
+            "Author" => Self::Author(String::new()),
+            "URL" => Self::Url(String::new()),
+            "Category" => Self::Category(String::new()),
+            "Feed" => Self::Feed(0),
+            _ => Self::Title(String::new()),
+        }
+    }
+
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        match self {
+            Self::Title(text) => contains(candidate.title, text),
+            Self::Content(text) => contains(&utils::strip_html(candidate.content), text),
+            Self::Author(text) => candidate.authors.iter().any(|a| contains(a, text)),
+            Self::Url(text) => contains(candidate.url, text),
+            Self::Category(text) => candidate.categories.iter().any(|c| contains(c, text)),
+            Self::Feed(id) => candidate.feed_id == *id,
+        }
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    let needle = needle.trim();
+    !needle.is_empty() && haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum Action {
+    MarkRead,
+    Star,
+    Tag(String),
+    /// shows the article in the view of a folder instead of the one of its feed
+    Folder(u64),
+    /// the article is not kept
+    Drop,
+}
+
+impl Action {
+    pub const KINDS: [&'static str; 5] = ["Mark as read", "Star", "Tag", "Move to folder", "Drop"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MarkRead => "Mark as read",
+            Self::Star => "Star",
+            Self::Tag(_) => "Tag",
+            Self::Folder(_) => "Move to folder",
+            Self::Drop => "Drop",
+        }
+    }
+
+    /// An action of a kind of [`Self::KINDS`].
+    pub fn of_kind(kind: &str) -> Self {
+        match kind {
+            "Star" => Self::Star,
+            "Tag" => Self::Tag(String::new()),
+            "Move to folder" => Self::Folder(1),
+            "Drop" => Self::Drop,
+            _ => Self::MarkRead,
+        }
+    }
+}
+
+/// What a rule looks at.
+#[derive(Clone, Default, Debug)]
+pub struct Candidate<'a> {
+    pub feed_id: u64,
+    pub title: &'a str,
+    /// html
+    pub content: &'a str,
+    pub url: &'a str,
+    pub authors: Vec<&'a str>,
+    pub categories: Vec<&'a str>,
+}
+
+impl Rule {
+    /// Disabled rules match too, rules without conditions don't.
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        !self.conditions.is_empty() && self.conditions.iter().all(|c| c.matches(candidate))
+    }
+}
+
+/// Actions of the matching rules on a new article.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub drop: bool,
+    pub read: bool,
+    pub star: bool,
+    pub tags: Vec<String>,
+    /// the first folder wins
+    pub folder: Option<u64>,
+}
+
+/// Runs the enabled rules in order.
+pub fn apply(rules: &[Rule], candidate: &Candidate) -> Outcome {
+    let mut outcome = Outcome::default();
+    for rule in rules.iter().filter(|r| r.enabled && r.matches(candidate)) {
+        for action in &rule.actions {
+            match action {
+                Action::MarkRead => outcome.read = true,
+                Action::Star => outcome.star = true,
+                Action::Tag(tag) => {
+                    let tag = tag.trim();
+                    if !tag.is_empty() && !outcome.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+                    {
+                        outcome.tags.push(tag.to_owned());
+                    }
+                }
+                Action::Folder(id) => {
+                    outcome.folder.get_or_insert(*id);
+                }
+                Action::Drop => outcome.drop = true,
+            }
+        }
+    }
+    outcome
+}
+
+/// Existing articles a rule would have matched.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct DryRun {
+    /// the latest articles looked at
+    pub scanned: usize,
+    /// older articles are not looked at
+    pub truncated: bool,
+    pub matched: usize,
+    /// the latest ones, without their content
+    pub articles: Vec<Article>,
+}
@@ -0,0 +1,135 @@
+//! Search queries, words and phrases are matched by the full-text index of
+//! articles and qualifiers narrow the results down.
+//!
+//! ```text
+//! rust "async closures" tok* feed:blog folder:"Tech News" author:steve tag:later is:unread
+//! ```
+
+use crate::models::ArticleSummary;
+
+/// Marks the start of a matched word in a snippet.
+pub const HIGHLIGHT_START: char = '\u{2}';
+/// Marks the end of a matched word in a snippet.
+pub const HIGHLIGHT_END: char = '\u{3}';
+
+/// A parsed search query.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Query {
+    /// an FTS5 query, every word and phrase is quoted
+    pub text: String,
+    pub filters: Filters,
+}
+
+/// Qualifiers of a query.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Filters {
+    /// part of the feed name, ignoring case
+    pub feed: Option<String>,
+    /// part of the folder name, ignoring case
+    pub folder: Option<String>,
+    /// words of an author name
+    pub author: Option<String>,
+    /// the name of a tag, ignoring case
+    pub tag: Option<String>,
+    pub unread: bool,
+}
+
+impl Query {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.filters == Filters::default()
+    }
+}
+
+/// An article found by a query, the best matches first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub article: ArticleSummary,
+    /// the best matching part of the article, the matched words are between
+    /// [`HIGHLIGHT_START`] and [`HIGHLIGHT_END`]
+    pub snippet: String,
+}
+
+/// Parses words, `"phrases"`, `prefixes*` and the `feed:`, `folder:`,
+/// `author:`, `tag:` and `is:unread` qualifiers, unknown qualifiers are words.
+pub fn parse(input: &str) -> Query {
+    let mut query = Query::default();
+    let mut terms = Vec::new();
+    for token in tokenize(input) {
+        let (key, value) = match token.split_once(':') {
+            Some((key, value)) if !token.starts_with('"') => (key, unquote(value)),
+            _ => ("", token.as_str()),
+        };
+        let value = value.trim();
+        match key.to_lowercase().as_str() {
+            _ if value.is_empty() => {}
+            "feed" => query.filters.feed = Some(value.to_owned()),
+            "folder" => query.filters.folder = Some(value.to_owned()),
+            "author" => query.filters.author = Some(value.to_owned()),
+            "tag" => query.filters.tag = Some(value.to_owned()),
+            "is" if value.eq_ignore_ascii_case("unread") => query.filters.unread = true,
+            _ => terms.push(term(&token)),
+        }
+    }
+    query.text = terms.into_iter().flatten().collect::<Vec<_>>().join(" ");
+    query
+}
+
+/// Splits on whitespace outside of double quotes.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches('"')
+}
+
+/// Quotes a word or a phrase for FTS5, keeps a trailing `*` as a prefix.
+fn term(token: &str) -> Option<String> {
+    let (token, prefix) = match token.strip_suffix('*') {
+        Some(token) => (token, "*"),
+        None => (token, ""),
+    };
+    let words = unquote(token).split_whitespace().collect::<Vec<_>>();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "\"{}\"{prefix}",
+        words.join(" ").replace('"', "\"\"")
+    ))
+}
+
+/// Splits a snippet into parts, the matched ones are `true`.
+pub fn highlights(snippet: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut rest = snippet;
+    while let Some((before, after)) = rest.split_once(HIGHLIGHT_START) {
+        parts.push((before, false));
+        let (matched, after) = after.split_once(HIGHLIGHT_END).unwrap_or((after, ""));
+        parts.push((matched, true));
+        rest = after;
+    }
+    parts.push((rest, false));
+    parts.retain(|(part, _)| !part.is_empty());
+    parts
+}
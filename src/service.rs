@@ -0,0 +1,1560 @@
+//! The worker behind the UI, commands are queued and each one is answered.
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use backon::{BackoffBuilder, ExponentialBuilder};
+use image::RgbaImage;
+use once_cell::sync::Lazy;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::FromSql;
+use tokio::{
+    sync::{
+        mpsc::UnboundedReceiver,
+        oneshot::{self, error::TryRecvError},
+        OwnedSemaphorePermit, Semaphore,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    db,
+    discovery::{self, Candidate},
+    errors::{
+        ExportError, FeedError, FetchError, FolderError, Gone, ImageError, ImportError, RuleError,
+    },
+    images::{self, ImageCache},
+    models::{
+        Article, ArticleSummary, Cursor, Feed, FeedPreview, FeedSettings, FetchState, Folder,
+        Notification, Progress, Pruned, Retention, Scope, SmartFolder, Tag, Timeline,
+    },
+    opml::{self, ImportReport},
+    rules::{DryRun, Rule},
+    search::{self, Hit},
+    utils,
+};
+
+/// seconds, used when the `refresh_interval` setting is missing
+const DEFAULT_REFRESH_INTERVAL: u64 = 30 * 60;
+/// seconds
+const MIN_REFRESH_INTERVAL: u64 = 60;
+/// entries shown by a preview
+const PREVIEW_ENTRIES: usize = 5;
+/// results of a search
+const SEARCH_LIMIT: usize = 100;
+/// articles listed by a dry run
+const DRY_RUN_EXAMPLES: usize = 20;
+/// latest articles a dry run looks at
+const DRY_RUN_SCAN: usize = 5_000;
+const DEFAULT_FETCH_CONCURRENCY: usize = 8;
+const DEFAULT_FETCH_HOST_CONCURRENCY: usize = 2;
+const DEFAULT_FETCH_RETRIES: usize = 3;
+const DEFAULT_DEAD_AFTER_FAILURES: u32 = 10;
+const RETRY_MIN_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+/// a longer `Retry-After` is left to the scheduler
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+const MAX_REDIRECTS: usize = 10;
+/// the interval of a failing feed doubles with each failure, up to this factor
+const MAX_BACKOFF_FACTOR: u32 = 32;
+const SCHEDULER_TICK: Duration = Duration::from_secs(30);
+/// first refreshes after launch are spread over this duration
+const STARTUP_SPREAD: Duration = Duration::from_secs(5 * 60);
+/// old articles are pruned once the first refreshes are done, then daily
+const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    use reqwest::header;
+    let mut headers = header::HeaderMap::new();
+    // headers.insert(
+    //     "Cache-Control",
+    //     header::HeaderValue::from_static("max-age=0"),
+    // );
+    // headers.insert("Connection", header::HeaderValue::from_static("keep-alive"));
+    reqwest::Client::builder()
+        // Cache-Control: max-age=0
+        // Connection: keep-alive
+        // .timeout(Duration::from_secs(10))
+        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36")
+        .default_headers(headers)
+        // .use_native_tls()
+        .trust_dns(true)
+        .gzip(true)
+        .deflate(true)
+        .brotli(true)
+        .timeout(Duration::from_secs(60))
+        // followed by `follow`, permanent moves are kept
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Cant build a reqwest client")
+});
+
+pub type Reply<T, E> = oneshot::Sender<Result<T, E>>;
+
+#[derive(Debug)]
+pub enum Command {
+    CreateFeed(Feed, Reply<Feed, FeedError>),
+    UpdateFeed(FeedSettings, Reply<Feed, FeedError>),
+    DeleteFeed(Feed, Reply<(), FeedError>),
+    /// answered when the fetch is done
+    FetchFeed(Feed, Reply<(), FetchError>),
+    /// answered with the number of queued feeds
+    FetchFolder(Folder, Reply<usize, FetchError>),
+    /// answered with the number of queued feeds
+    FetchAll(Reply<usize, FetchError>),
+    /// answered with the number of changed articles, `Some` only changes the
+    /// articles published before it
+    MarkRead(Scope, Option<i64>, Reply<usize, FeedError>),
+    MarkUnread(ArticleSummary, Reply<(), FeedError>),
+    /// answered with the star time, `None` once unstarred
+    Star(u64, bool, Reply<Option<i64>, FeedError>),
+    /// replaces the tags of an article, answered with the stored ones
+    SetTags(u64, Vec<String>, Reply<Vec<String>, FeedError>),
+    /// a page of at most `usize` articles after the cursor, `None` for the
+    /// first page
+    Timeline(
+        Timeline,
+        Option<Cursor>,
+        usize,
+        Reply<Vec<ArticleSummary>, FeedError>,
+    ),
+    /// an article with its content, to be read
+    Article(u64, Reply<Article, FeedError>),
+    /// an image of an article, from the cache or fetched
+    Image(String, Reply<RgbaImage, ImageError>),
+    /// articles matching a search query
+    Search(String, Reply<Vec<Hit>, FeedError>),
+    /// finds feeds of a website
+    Discover(String, Reply<Vec<Candidate>, FetchError>),
+    /// fetches and parses a feed without subscribing
+    Preview(String, Reply<FeedPreview, FetchError>),
+    CreateFolder(Folder, Reply<Folder, FolderError>),
+    /// subscribes to the feeds of an OPML file
+    ImportOpml(PathBuf, Reply<ImportReport, ImportError>),
+    /// answered with the number of exported feeds
+    ExportOpml(PathBuf, Reply<usize, ExportError>),
+    RenameFolder(Folder, Reply<Folder, FolderError>),
+    /// feeds are moved to the default folder
+    DeleteFolder(Folder, Reply<(), FolderError>),
+    CreateSmartFolder(SmartFolder, Reply<SmartFolder, FolderError>),
+    UpdateSmartFolder(SmartFolder, Reply<SmartFolder, FolderError>),
+    DeleteSmartFolder(SmartFolder, Reply<(), FolderError>),
+    Rules(Reply<Vec<Rule>, RuleError>),
+    /// creates the rule when its id is `0`
+    SaveRule(Rule, Reply<Rule, RuleError>),
+    DeleteRule(Rule, Reply<(), RuleError>),
+    /// existing articles a rule would have matched
+    DryRun(Rule, Reply<DryRun, RuleError>),
+    Retention(Reply<Retention, FeedError>),
+    SetRetention(Retention, Reply<Retention, FeedError>),
+    /// deletes old articles and gives their space back
+    Prune(Reply<Pruned, FeedError>),
+}
+
+/// The outcome of a command, can be polled every frame or awaited.
+#[derive(Debug)]
+pub struct Pending<T, E>(oneshot::Receiver<Result<T, E>>);
+
+impl<T, E: From<Gone>> Pending<T, E> {
+    pub fn new(receiver: oneshot::Receiver<Result<T, E>>) -> Self {
+        Self(receiver)
+    }
+
+    /// `None` while the command is running.
+    pub fn try_recv(&mut self) -> Option<Result<T, E>> {
+        match self.0.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => Some(Err(Gone.into())),
+        }
+    }
+
+    pub async fn recv(self) -> Result<T, E> {
+        self.0.await.map_err(|_| Gone)?
+    }
+}
+
+/// Handles commands, and fetches feeds with bounded concurrency.
+#[derive(Clone)]
+pub struct Worker {
+    pool: Pool<SqliteConnectionManager>,
+    folders: Arc<RwLock<Vec<Folder>>>,
+    /// with their unread counts
+    smart_folders: Arc<RwLock<Vec<SmartFolder>>>,
+    /// with their unread counts
+    tags: Arc<RwLock<Vec<Tag>>>,
+    progress: Arc<RwLock<Progress>>,
+    notifications: Arc<RwLock<Vec<Notification>>>,
+    images: ImageCache,
+    limits: Limits,
+    /// retries of a request on transient failures
+    retries: usize,
+    /// consecutive failures before a feed is no longer refreshed
+    dead_after: u32,
+}
+
+impl Worker {
+    pub fn new(
+        pool: Pool<SqliteConnectionManager>,
+        folders: Arc<RwLock<Vec<Folder>>>,
+        smart_folders: Arc<RwLock<Vec<SmartFolder>>>,
+        tags: Arc<RwLock<Vec<Tag>>>,
+        progress: Arc<RwLock<Progress>>,
+        notifications: Arc<RwLock<Vec<Notification>>>,
+        images: ImageCache,
+    ) -> Self {
+        let worker = Self {
+            limits: Limits::new(
+                setting(&pool, db::FETCH_CONCURRENCY).unwrap_or(DEFAULT_FETCH_CONCURRENCY),
+                setting(&pool, db::FETCH_HOST_CONCURRENCY)
+                    .unwrap_or(DEFAULT_FETCH_HOST_CONCURRENCY),
+            ),
+            retries: setting(&pool, db::FETCH_RETRIES).unwrap_or(DEFAULT_FETCH_RETRIES),
+            dead_after: setting(&pool, db::DEAD_AFTER_FAILURES)
+                .unwrap_or(DEFAULT_DEAD_AFTER_FAILURES),
+            pool,
+            folders,
+            smart_folders,
+            tags,
+            progress,
+            notifications,
+            images,
+        };
+        worker.refresh_smart_folders();
+        worker.refresh_tags();
+        worker
+    }
+
+    pub async fn run(self, mut receiver: UnboundedReceiver<Command>) {
+        while let Some(command) = receiver.recv().await {
+            self.handle(command);
+        }
+    }
+
+    pub fn handle(&self, command: Command) {
+        tracing::info!("{:?}", command);
+        match command {
+            Command::CreateFeed(feed, reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.create_feed(feed).await);
+                });
+            }
+            Command::UpdateFeed(settings, reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.update_feed(settings).await);
+                });
+            }
+            Command::DeleteFeed(feed, reply) => {
+                let _ = reply.send(self.delete_feed(feed));
+            }
+            Command::FetchFeed(feed, reply) => {
+                let name = feed.name.clone();
+                match self.fetch(feed, true) {
+                    Some(handle) => {
+                        let worker = self.clone();
+                        tokio::task::spawn(async move {
+                            let result = handle.await.map_err(FetchError::from).and_then(|r| r);
+                            worker.report(&name, &result);
+                            let _ = reply.send(result);
+                        });
+                    }
+                    None => {
+                        let _ = reply.send(Ok(()));
+                    }
+                }
+            }
+            Command::FetchFolder(folder, reply) => {
+                let _ = reply.send(Ok(self.fetch_folder(Some(folder.id))));
+            }
+            Command::FetchAll(reply) => {
+                let _ = reply.send(Ok(self.fetch_folder(None)));
+            }
+            Command::MarkRead(scope, before, reply) => {
+                let _ = reply.send(self.mark_read(scope, before));
+            }
+            Command::MarkUnread(article, reply) => {
+                let _ = reply.send(self.mark_unread(article));
+            }
+            Command::Star(id, starred, reply) => {
+                let _ = reply.send(self.star(id, starred));
+            }
+            Command::SetTags(id, tags, reply) => {
+                let _ = reply.send(self.set_tags(id, tags));
+            }
+            Command::Timeline(timeline, after, limit, reply) => {
+                let _ = reply.send(self.timeline(timeline, after, limit));
+            }
+            Command::Article(id, reply) => {
+                let _ = reply.send(self.article(id));
+            }
+            Command::Image(url, reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.image(url).await);
+                });
+            }
+            Command::Search(query, reply) => {
+                let _ = reply.send(self.search(&query));
+            }
+            Command::Discover(url, reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.discover(&url).await);
+                });
+            }
+            Command::Preview(url, reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.preview(&url).await);
+                });
+            }
+            Command::ImportOpml(path, reply) => {
+                let _ = reply.send(self.import_opml(path));
+            }
+            Command::ExportOpml(path, reply) => {
+                let _ = reply.send(self.export_opml(path));
+            }
+            Command::CreateFolder(folder, reply) => {
+                let _ = reply.send(self.create_folder(folder));
+            }
+            Command::RenameFolder(folder, reply) => {
+                let _ = reply.send(self.rename_folder(folder));
+            }
+            Command::DeleteFolder(folder, reply) => {
+                let _ = reply.send(self.delete_folder(folder));
+            }
+            Command::CreateSmartFolder(folder, reply) => {
+                let _ = reply.send(self.create_smart_folder(folder));
+            }
+            Command::UpdateSmartFolder(folder, reply) => {
+                let _ = reply.send(self.update_smart_folder(folder));
+            }
+            Command::DeleteSmartFolder(folder, reply) => {
+                let _ = reply.send(self.delete_smart_folder(folder));
+            }
+            Command::Rules(reply) => {
+                let _ = reply.send(self.rules());
+            }
+            Command::SaveRule(rule, reply) => {
+                let _ = reply.send(self.save_rule(rule));
+            }
+            Command::DeleteRule(rule, reply) => {
+                let _ = reply.send(self.delete_rule(rule));
+            }
+            Command::DryRun(rule, reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.dry_run(rule).await);
+                });
+            }
+            Command::Retention(reply) => {
+                let _ = reply.send(self.retention());
+            }
+            Command::SetRetention(retention, reply) => {
+                let _ = reply.send(self.set_retention(retention));
+            }
+            Command::Prune(reply) => {
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    let _ = reply.send(worker.prune().await);
+                });
+            }
+        }
+    }
+
+    fn notify(&self, notification: Notification) {
+        if let Ok(mut notifications) = self.notifications.write() {
+            notifications.push(notification);
+        }
+    }
+
+    /// Tells the user about a failed fetch they asked for.
+    fn report(&self, name: &str, result: &Result<(), FetchError>) {
+        if let Err(e) = result {
+            self.notify(Notification::error(format!("{name} failed: {e}")));
+        }
+    }
+
+    /// Only urls which parse as a feed are subscribed, an empty name falls back
+    /// to the title of the feed.
+    async fn create_feed(&self, mut feed: Feed) -> Result<Feed, FeedError> {
+        feed.url = feed.url.trim().to_owned();
+        feed.name = feed.name.trim().to_owned();
+        // the url could be an old one of a moved feed
+        let subscribed = db::find_feed_by_url(&mut self.pool.get()?, &feed.url)?;
+        if let Some((_, name)) = subscribed {
+            return Err(FeedError::SubscribedAs(name));
+        }
+        let preview = self.preview(&feed.url).await?;
+        if feed.name.is_empty() {
+            feed.name = preview
+                .title
+                .map(|t| t.trim().to_owned())
+                .filter(|t| !t.is_empty())
+                .unwrap_or_else(|| feed.url.clone());
+        }
+
+        let mut conn = self.pool.get()?;
+        feed.id = db::create_feed(&mut conn, &feed)?;
+        self.folders.write().ok().and_then(|mut folders| {
+            folders
+                .iter_mut()
+                .find(|f| f.id == feed.folder_id)
+                .map(|f| f.feeds.get_or_insert_with(Vec::new).push(feed.clone()))
+        });
+        Ok(feed)
+    }
+
+    /// Only the edited settings are copied onto the feed, its fetch state,
+    /// unread count, etc are kept.
+    async fn update_feed(&self, mut settings: FeedSettings) -> Result<Feed, FeedError> {
+        settings.url = settings.url.trim().to_owned();
+        if self
+            .folders
+            .read()
+            .ok()
+            .and_then(|folders| {
+                folders
+                    .iter()
+                    .filter_map(|f| f.feeds.as_ref())
+                    .flatten()
+                    .find(|f| f.id == settings.id)
+                    .map(|f| f.url != settings.url)
+            })
+            .unwrap_or_default()
+        {
+            // a new url must be a feed too
+            self.preview(&settings.url).await?;
+        }
+        let mut conn = self.pool.get()?;
+        let (prev_folder_id, changed) = db::update_feed(&mut conn, &settings)?;
+        // categories of stored articles are imported once enabled
+        self.refresh_tags();
+        let mut feed = None;
+        if let Ok(mut folders) = self.folders.write() {
+            // dont change folder
+            let folder_id = if prev_folder_id == 0 {
+                settings.folder_id
+            } else {
+                prev_folder_id
+            };
+            // update
+            if changed > 0 {
+                if let Some(f) = folders
+                    .iter_mut()
+                    .find(|f| f.id == folder_id)
+                    .and_then(|f| f.feeds.as_mut())
+                    .and_then(|feeds| feeds.iter_mut().find(|f| f.id == settings.id))
+                {
+                    settings.apply(f);
+                    feed = Some(f.clone());
+                }
+            }
+            if prev_folder_id != 0 {
+                folders
+                    .iter_mut()
+                    .filter(|f| f.id == prev_folder_id || f.id == settings.folder_id)
+                    .for_each(|f| {
+                        if f.id == prev_folder_id {
+                            // delete from prev folder
+                            if let Some(feeds) = f.feeds.as_mut() {
+                                feeds.retain(|f| f.id != settings.id);
+                            }
+                        } else if let Some(feed) = &feed {
+                            // push to new folder
+                            f.feeds.get_or_insert_with(Vec::new).push(feed.clone())
+                        }
+                    })
+            }
+        }
+        Ok(feed.unwrap_or_else(|| {
+            let mut feed = Feed::default();
+            settings.apply(&mut feed);
+            feed
+        }))
+    }
+
+    fn delete_feed(&self, feed: Feed) -> Result<(), FeedError> {
+        let mut conn = self.pool.get()?;
+        db::delete_feed(&mut conn, &feed)?;
+        self.folders.write().ok().and_then(|mut folders| {
+            folders
+                .iter_mut()
+                .find(|f| f.id == feed.folder_id)
+                .and_then(|f| f.feeds.as_mut())
+                .map(|feeds| feeds.retain(|f| f.id != feed.id))
+        });
+        Ok(())
+    }
+
+    fn create_folder(&self, mut folder: Folder) -> Result<Folder, FolderError> {
+        let mut conn = self.pool.get()?;
+        folder.id = db::create_folder(&mut conn, &folder)?;
+        if let Ok(mut folders) = self.folders.write() {
+            folders.push(folder.clone());
+        }
+        Ok(folder)
+    }
+
+    /// New feeds are left to the scheduler.
+    fn import_opml(&self, path: PathBuf) -> Result<ImportReport, ImportError> {
+        let opml = opml::parse(&fs::read_to_string(&path)?)?;
+        let (imported, skipped) = db::import_opml(&mut self.pool.get()?, &opml)?;
+        let report = ImportReport {
+            added: imported
+                .iter()
+                .filter_map(|f| f.feeds.as_ref())
+                .map(Vec::len)
+                .sum(),
+            skipped,
+            invalid: opml.invalid.len(),
+        };
+
+        if let Ok(mut folders) = self.folders.write() {
+            for folder in imported {
+                match folders.iter_mut().find(|f| f.id == folder.id) {
+                    Some(f) => f
+                        .feeds
+                        .get_or_insert_with(Vec::new)
+                        .extend(folder.feeds.unwrap_or_default()),
+                    None => folders.push(folder),
+                }
+            }
+        }
+        tracing::info!("{}: {report}", path.display());
+        for url in &opml.invalid {
+            tracing::warn!("{}: invalid feed url {url}", path.display());
+        }
+        self.notify(Notification::info(format!("OPML imported: {report}")));
+        Ok(report)
+    }
+
+    fn export_opml(&self, path: PathBuf) -> Result<usize, ExportError> {
+        let (feeds, xml) = export_opml(&mut self.pool.get()?)?;
+        fs::write(&path, xml)?;
+        tracing::info!("{}: exported {feeds} feeds", path.display());
+        Ok(feeds)
+    }
+
+    fn rename_folder(&self, folder: Folder) -> Result<Folder, FolderError> {
+        let mut conn = self.pool.get()?;
+        if db::rename_folder(&mut conn, &folder)? == 1 {
+            self.folders.write().ok().map(|mut folders| {
+                folders
+                    .iter_mut()
+                    .find(|f| f.id == folder.id)
+                    .map(|f| f.name = folder.name.to_owned())
+            });
+        }
+        Ok(folder)
+    }
+
+    fn delete_folder(&self, folder: Folder) -> Result<(), FolderError> {
+        let mut conn = self.pool.get()?;
+        // mv other folder's feeds to folder 1
+        db::delete_folder(&mut conn, &folder)?;
+        if let Ok(mut folders) = self.folders.write() {
+            let Some(i) = folders.iter().position(|f| f.id == folder.id) else {
+                return Ok(());
+            };
+            let removed = folders.remove(i);
+            if let Some(feeds) = removed.feeds {
+                if let Some(f) = folders.iter_mut().find(|f| f.id == 1) {
+                    f.feeds
+                        .get_or_insert_with(Vec::new)
+                        .extend(feeds.into_iter().map(|mut feed| {
+                            feed.folder_id = 1;
+                            feed
+                        }))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn create_smart_folder(&self, mut folder: SmartFolder) -> Result<SmartFolder, FolderError> {
+        folder.id = db::create_smart_folder(&mut self.pool.get()?, &folder)?;
+        self.refresh_smart_folders();
+        Ok(folder)
+    }
+
+    fn update_smart_folder(&self, folder: SmartFolder) -> Result<SmartFolder, FolderError> {
+        db::update_smart_folder(&mut self.pool.get()?, &folder)?;
+        self.refresh_smart_folders();
+        Ok(folder)
+    }
+
+    fn delete_smart_folder(&self, folder: SmartFolder) -> Result<(), FolderError> {
+        db::delete_smart_folder(&mut self.pool.get()?, folder.id)?;
+        self.refresh_smart_folders();
+        Ok(())
+    }
+
+    fn rules(&self) -> Result<Vec<Rule>, RuleError> {
+        Ok(db::fetch_rules(&mut self.pool.get()?)?)
+    }
+
+    fn save_rule(&self, mut rule: Rule) -> Result<Rule, RuleError> {
+        if rule.conditions.is_empty() || rule.actions.is_empty() {
+            return Err(RuleError::Incomplete);
+        }
+        rule.id = db::save_rule(&mut self.pool.get()?, &rule)?;
+        Ok(rule)
+    }
+
+    fn delete_rule(&self, rule: Rule) -> Result<(), RuleError> {
+        db::delete_rule(&mut self.pool.get()?, rule.id)?;
+        Ok(())
+    }
+
+    /// Articles are scanned off the worker, it keeps handling commands.
+    async fn dry_run(&self, rule: Rule) -> Result<DryRun, RuleError> {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            Ok(db::dry_run_rule(
+                &mut pool.get()?,
+                &rule,
+                DRY_RUN_SCAN,
+                DRY_RUN_EXAMPLES,
+            )?)
+        })
+        .await?
+    }
+
+    /// Reloads smart folders, their unread counts change with new articles,
+    /// reads and stars.
+    fn refresh_smart_folders(&self) {
+        let result = self
+            .pool
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut conn| db::fetch_smart_folders(&mut conn));
+        match result {
+            Ok(folders) => {
+                if let Ok(mut smart_folders) = self.smart_folders.write() {
+                    *smart_folders = folders;
+                }
+            }
+            Err(e) => tracing::error!("smart folders: {e}"),
+        }
+    }
+
+    /// Reloads tags, their unread counts change with new articles, reads and
+    /// tags.
+    fn refresh_tags(&self) {
+        let result = self
+            .pool
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut conn| db::fetch_tags(&mut conn));
+        match result {
+            Ok(tags) => {
+                if let Ok(mut shared) = self.tags.write() {
+                    *shared = tags;
+                }
+            }
+            Err(e) => tracing::error!("tags: {e}"),
+        }
+    }
+
+    /// Fetches every feed of the folder, `None` for all folders, failures are
+    /// reported once each fetch is done.
+    fn fetch_folder(&self, folder_id: Option<u64>) -> usize {
+        let feeds = self
+            .folders
+            .read()
+            .map(|folders| {
+                folders
+                    .iter()
+                    .filter(|f| folder_id.filter(|id| *id != f.id).is_none())
+                    .filter_map(|f| f.feeds.as_ref())
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        tracing::info!("refresh {} feeds of {:?}", feeds.len(), folder_id);
+
+        let handles = feeds
+            .into_iter()
+            .filter_map(|feed| {
+                let name = feed.name.clone();
+                self.fetch(feed, true).map(|handle| (name, handle))
+            })
+            .collect::<Vec<_>>();
+
+        let n = handles.len();
+        let worker = self.clone();
+        tokio::task::spawn(async move {
+            for (name, handle) in handles {
+                let result = handle.await.map_err(FetchError::from).and_then(|r| r);
+                worker.report(&name, &result);
+            }
+        });
+        n
+    }
+
+    /// Updates a feed of the folders in place, `false` when it is gone.
+    fn with_feed(&self, folder_id: u64, feed_id: u64, f: impl FnOnce(&mut Feed)) -> bool {
+        self.folders
+            .write()
+            .ok()
+            .and_then(|mut folders| {
+                folders
+                    .iter_mut()
+                    .find(|f| f.id == folder_id)
+                    .and_then(|f| f.feeds.as_mut())
+                    .and_then(|feeds| feeds.iter_mut().find(|f| f.id == feed_id))
+                    .map(f)
+            })
+            .is_some()
+    }
+
+    fn mark_read(&self, scope: Scope, before: Option<i64>) -> Result<usize, FeedError> {
+        let at = chrono::Utc::now().timestamp_millis();
+        let changed = db::mark_read(&mut self.pool.get()?, scope, before, at)?;
+        self.with_feeds(|feed| {
+            if let Some(n) = changed.get(&feed.id) {
+                feed.unread = feed.unread.saturating_sub(*n);
+            }
+        });
+        self.refresh_smart_folders();
+        self.refresh_tags();
+        Ok(changed.values().sum())
+    }
+
+    fn mark_unread(&self, article: ArticleSummary) -> Result<(), FeedError> {
+        if db::mark_unread(&mut self.pool.get()?, article.id)? == 0 {
+            return Ok(());
+        }
+        self.with_feeds(|feed| {
+            if feed.id == article.feed_id {
+                feed.unread += 1;
+            }
+        });
+        self.refresh_smart_folders();
+        self.refresh_tags();
+        Ok(())
+    }
+
+    fn star(&self, id: u64, starred: bool) -> Result<Option<i64>, FeedError> {
+        let at = starred.then(|| chrono::Utc::now().timestamp_millis());
+        db::star_article(&mut self.pool.get()?, id, at)?;
+        self.refresh_smart_folders();
+        Ok(at)
+    }
+
+    fn set_tags(&self, id: u64, tags: Vec<String>) -> Result<Vec<String>, FeedError> {
+        let tags = db::set_article_tags(&mut self.pool.get()?, id, &tags)?;
+        self.refresh_tags();
+        Ok(tags)
+    }
+
+    fn timeline(
+        &self,
+        timeline: Timeline,
+        after: Option<Cursor>,
+        limit: usize,
+    ) -> Result<Vec<ArticleSummary>, FeedError> {
+        let since = chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+            .map(|midnight| midnight.timestamp_millis())
+            .unwrap_or_default();
+        Ok(db::find_timeline_articles(
+            &mut self.pool.get()?,
+            timeline,
+            since,
+            after,
+            limit,
+        )?)
+    }
+
+    fn article(&self, id: u64) -> Result<Article, FeedError> {
+        Ok(db::find_article(&mut self.pool.get()?, id)?)
+    }
+
+    /// An image from the cache, or fetched then cached. Fetching is not
+    /// limited like feeds, the reader waits for it.
+    async fn image(&self, url: String) -> Result<RgbaImage, ImageError> {
+        let cache = self.images.clone();
+        let key = url.clone();
+        if let Some(bytes) = tokio::task::spawn_blocking(move || cache.get(&key)).await? {
+            return Ok(tokio::task::spawn_blocking(move || images::decode(&bytes)).await??);
+        }
+
+        let mut resp = get(&url, self.retries).await?;
+        if let Some(len) = resp
+            .content_length()
+            .filter(|len| *len > images::MAX_IMAGE_SIZE)
+        {
+            return Err(ImageError::TooLarge(len));
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = resp.chunk().await.map_err(FetchError::from)? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() as u64 > images::MAX_IMAGE_SIZE {
+                return Err(ImageError::TooLarge(bytes.len() as u64));
+            }
+        }
+
+        let cache = self.images.clone();
+        tokio::task::spawn_blocking(move || {
+            let image = images::decode(&bytes)?;
+            // only images which decode are kept
+            if let Err(e) = cache.insert(&url, &bytes) {
+                tracing::error!("can't cache {url}: {e}");
+            }
+            Ok(image)
+        })
+        .await?
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<Hit>, FeedError> {
+        let query = search::parse(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(db::search_articles(
+            &mut self.pool.get()?,
+            &query.text,
+            &query.filters,
+            SEARCH_LIMIT,
+        )?)
+    }
+
+    fn with_feeds(&self, f: impl FnMut(&mut Feed)) {
+        if let Ok(mut folders) = self.folders.write() {
+            folders
+                .iter_mut()
+                .filter_map(|folder| folder.feeds.as_mut())
+                .flatten()
+                .for_each(f);
+        }
+    }
+
+    fn retention(&self) -> Result<Retention, FeedError> {
+        let mut conn = self.pool.get()?;
+        Ok(Retention {
+            keep_days: db::get_setting(&mut conn, db::KEEP_DAYS)?.unwrap_or_default(),
+            keep_articles: db::get_setting(&mut conn, db::KEEP_ARTICLES)?.unwrap_or_default(),
+        })
+    }
+
+    fn set_retention(&self, retention: Retention) -> Result<Retention, FeedError> {
+        let mut conn = self.pool.get()?;
+        db::set_setting(&mut conn, db::KEEP_DAYS, retention.keep_days)?;
+        db::set_setting(&mut conn, db::KEEP_ARTICLES, retention.keep_articles)?;
+        Ok(retention)
+    }
+
+    /// Deletes old read articles, then vacuums the freed pages. Both run off
+    /// the worker, it keeps handling commands.
+    async fn prune(&self) -> Result<Pruned, FeedError> {
+        let worker = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let Retention {
+                keep_days,
+                keep_articles,
+            } = worker.retention()?;
+            let mut conn = worker.pool.get()?;
+            let ids = db::prune_articles(
+                &mut conn,
+                chrono::Utc::now().timestamp_millis(),
+                keep_days,
+                keep_articles,
+            )?;
+            if !ids.is_empty() {
+                worker.refresh_tags();
+            }
+            let freed = db::vacuum(&mut conn)?;
+            tracing::info!("pruned {} articles, freed {freed} bytes", ids.len());
+            Ok(Pruned {
+                articles: ids.len(),
+                freed,
+            })
+        })
+        .await?
+    }
+
+    /// Finds feeds of a page, or the page itself when it is a feed. Declared
+    /// feeds are preferred, guesses are checked only when there are none.
+    async fn discover(&self, url: &str) -> Result<Vec<Candidate>, FetchError> {
+        let resp = get(url.trim(), self.retries).await?;
+        let base = resp.url().clone();
+        let kind = content_type(&resp);
+        let data = resp.bytes().await?;
+        if let Ok(feed) = feed_rs::parser::parse(data.as_ref()) {
+            return Ok(vec![Candidate {
+                url: base.to_string(),
+                title: feed.title.map(|t| t.content),
+                kind,
+            }]);
+        }
+
+        let (mut found, mut guesses): (Vec<_>, Vec<_>) =
+            discovery::parse_candidates(&base, &String::from_utf8_lossy(&data))
+                .into_iter()
+                .partition(|c| c.kind.is_some());
+        if !found.is_empty() {
+            return Ok(found);
+        }
+
+        guesses.extend(discovery::common_paths(&base));
+        let handles = guesses
+            .into_iter()
+            .map(|guess| {
+                let worker = self.clone();
+                tokio::task::spawn(async move { worker.probe(guess).await })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            if let Ok(Some(candidate)) = handle.await {
+                if !found.iter().any(|c| c.url == candidate.url) {
+                    found.push(candidate);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Keeps a guess when it can be parsed as a feed.
+    async fn probe(&self, mut guess: Candidate) -> Option<Candidate> {
+        let permits = self.limits.acquire(&guess.url).await;
+        let resp = get(&guess.url, 0).await.ok()?;
+        guess.url = resp.url().to_string();
+        guess.kind = content_type(&resp);
+        let feed = feed_rs::parser::parse(resp.bytes().await.ok()?.as_ref()).ok()?;
+        drop(permits);
+        if let Some(title) = feed.title {
+            guess.title = Some(title.content);
+        }
+        Some(guess)
+    }
+
+    /// Fetches and parses a feed, its latest entries come first.
+    async fn preview(&self, url: &str) -> Result<FeedPreview, FetchError> {
+        let url = url.trim();
+        let permits = self.limits.acquire(url).await;
+        let resp = get(url, self.retries).await?;
+        let url = resp.url().to_string();
+        let feed = feed_rs::parser::parse(resp.bytes().await?.as_ref())?;
+        drop(permits);
+
+        let mut entries = feed
+            .entries
+            .into_iter()
+            .map(|e| (e.updated.or(e.published), e.title.map(|t| t.content)))
+            .collect::<Vec<_>>();
+        // undated entries keep their order
+        entries.sort_by_key(|(date, _)| Reverse(*date));
+        Ok(FeedPreview {
+            url,
+            title: feed.title.map(|t| t.content),
+            feed_type: feed.feed_type,
+            description: feed.description.map(|t| t.content),
+            entries: entries.len(),
+            latest: entries
+                .into_iter()
+                .filter_map(|(_, title)| title)
+                .take(PREVIEW_ENTRIES)
+                .collect(),
+        })
+    }
+
+    /// The feed moved permanently, keeps the new url and the redirect in its
+    /// history, unless the new url is subscribed by another feed.
+    fn move_feed(
+        &self,
+        conn: &mut PooledConnection<SqliteConnectionManager>,
+        feed: &mut Feed,
+        (url, status): (String, u16),
+    ) -> Result<(), FetchError> {
+        if let Some((_, name)) = db::find_feed_by_url(conn, &url)?.filter(|(id, _)| *id != feed.id)
+        {
+            self.notify(Notification::error(format!(
+                "{} moved to {url}, which is subscribed as '{name}'",
+                feed.name
+            )));
+            return Ok(());
+        }
+
+        // only the url, the settings could be edited while fetching
+        db::update_feed_url(conn, feed.id, &url)?;
+        db::create_feed_redirect(conn, feed.id, &feed.url, &url, status)?;
+
+        // the name could be edited while fetching
+        let mut name = feed.name.clone();
+        self.with_feed(feed.folder_id, feed.id, |f| {
+            f.moved_from = Some(feed.url.clone());
+            f.url = url.clone();
+            // validators belong to the previous url
+            f.etag = None;
+            f.last_modified = None;
+            name = f.name.clone();
+        });
+        tracing::info!("{}: moved to {url}", feed.name);
+        self.notify(Notification::info(format!("{name} moved to {url}")));
+        feed.moved_from = Some(std::mem::replace(&mut feed.url, url));
+        Ok(())
+    }
+
+    /// Fetches a feed in background, skips it when it is loading. A pooled
+    /// connection is only taken once the response is in, waiting fetches
+    /// would hold them all. Only `tracked` fetches, started by the user, are
+    /// counted by the progress.
+    fn fetch(&self, mut feed: Feed, tracked: bool) -> Option<JoinHandle<Result<(), FetchError>>> {
+        let folder_id = feed.folder_id;
+        let feed_id = feed.id;
+
+        let mut busy = false;
+        self.with_feed(folder_id, feed_id, |f| {
+            // `queued`, `fetching`, etc, pass
+            if f.state.is_busy() {
+                busy = true;
+                return;
+            }
+            f.state = FetchState::Queued;
+        });
+        if busy {
+            return None;
+        }
+
+        let url = feed.url.clone();
+        let etag = feed.etag.clone();
+        let last_modified = feed.last_modified.clone();
+        let name = feed.name.clone();
+        let worker = self.clone();
+        if tracked {
+            if let Ok(mut progress) = self.progress.write() {
+                progress.start();
+            }
+        }
+        let handle = tokio::task::spawn(async move {
+            let permits = worker.limits.acquire(&url).await;
+            worker.with_feed(folder_id, feed_id, |f| f.state = FetchState::Fetching);
+            let result = async {
+                let (status, etag, last_modified, data, moved) = {
+                    use reqwest::{header, StatusCode};
+
+                    let request = |url| {
+                        let mut req = CLIENT.get(url);
+                        if let Some(etag) = &etag {
+                            req = req.header(header::IF_NONE_MATCH, etag);
+                        }
+                        if let Some(last_modified) = &last_modified {
+                            req = req.header(header::IF_MODIFIED_SINCE, last_modified);
+                        }
+                        req
+                    };
+                    let (resp, moved) = follow(request, &url, worker.retries).await?;
+                    let status = resp.status();
+                    if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+                        return Err(FetchError::Status(status));
+                    }
+                    let value = |name| {
+                        resp.headers()
+                            .get(name)
+                            .and_then(|v| v.to_str().ok())
+                            .map(ToOwned::to_owned)
+                    };
+                    let etag = value(header::ETAG);
+                    let last_modified = value(header::LAST_MODIFIED);
+                    let data = if status == StatusCode::NOT_MODIFIED {
+                        None
+                    } else {
+                        Some(resp.bytes().await?)
+                    };
+                    (status, etag, last_modified, data, moved)
+                };
+
+                let mut conn = worker.pool.get()?;
+                let http_status = status.as_u16();
+                let fetched = chrono::Utc::now().timestamp_millis();
+                // validators are kept only after the response is handled,
+                // a failed parse must not turn into `304 Not Modified` later
+                let fetched_feed = |conn: &mut _, state| {
+                    db::update_feed_fetched(
+                        conn,
+                        feed_id,
+                        etag.as_ref(),
+                        last_modified.as_ref(),
+                        http_status,
+                        fetched,
+                    )?;
+                    worker.with_feed(folder_id, feed_id, |f| {
+                        if etag.is_some() {
+                            f.etag = etag.clone();
+                        }
+                        if last_modified.is_some() {
+                            f.last_modified = last_modified.clone();
+                        }
+                        f.http_status = Some(http_status);
+                        f.last_fetched = Some(fetched);
+                        f.last_error = None;
+                        f.failures = 0;
+                        f.dead = false;
+                        f.retry_at = None;
+                        f.state = state;
+                    });
+                    Ok::<(), FetchError>(())
+                };
+
+                // moved only once the new url answers with a feed
+                let Some(data) = data else {
+                    if let Some(moved) = moved {
+                        worker.move_feed(&mut conn, &mut feed, moved)?;
+                    }
+                    tracing::info!("{}: not modified", feed.name);
+                    return fetched_feed(&mut conn, FetchState::NotModified);
+                };
+
+                worker.with_feed(folder_id, feed_id, |f| f.state = FetchState::Parsing);
+
+                let feed_rs::model::Feed {
+                    feed_type,
+                    title,
+                    description,
+                    mut entries,
+                    published,
+                    updated,
+                    authors,
+                    links,
+                    ..
+                    // logo,
+                    // icon,
+                    // categories,
+                    // contributors,
+                    // published,
+                    // ttl,
+                    // language,
+                    // rating,
+                    // rights,
+                    // generator,
+                } = feed_rs::parser::parse(data.as_ref())?;
+
+                if let Some(moved) = moved {
+                    worker.move_feed(&mut conn, &mut feed, moved)?;
+                }
+
+                // @TODO: pre-processing entries data, then diff & update
+                // folders data
+
+                let published = entries
+                    .first()
+                    .and_then(|e| e.updated.or(e.published))
+                    .or(updated.or(published))
+                    .map(|t| t.timestamp_millis())
+                    .unwrap_or(feed.last_seen);
+
+                // sometimes some feed is non-standard, `updated` and
+                // `published` can not be parsed.
+                let flag = published > feed.last_seen
+                    || (published == feed.last_seen && !entries.is_empty());
+
+                tracing::info!(
+                    "{}: has new entries {}, last_seen = {}, published = {}, has {} entries",
+                    feed.name,
+                    flag,
+                    feed.last_seen,
+                    published,
+                    entries.len()
+                );
+
+                if !flag {
+                    return fetched_feed(&mut conn, FetchState::Idle);
+                }
+
+                worker.with_feed(folder_id, feed_id, |f| f.state = FetchState::Storing);
+
+                let site = utils::extract_site_url(feed.url.clone(), links);
+                let kind = format!("{feed_type:?}");
+
+                let (published, new) = db::update_feed_ext_and_upsert_articles(
+                    &mut conn,
+                    &feed,
+                    db::FeedExt {
+                        site: site.clone(),
+                        kind: feed_type,
+                        title: title.map(|t| t.content),
+                        description: description.map(|t| t.content),
+                    },
+                    published,
+                    authors,
+                    {
+                        // insert, order by asc
+                        entries.reverse();
+                        entries
+                    },
+                )?;
+
+                let unread = db::count_unread(&mut conn, feed_id).ok();
+
+                worker.with_feed(folder_id, feed_id, |f| {
+                    f.site = Some(site.clone());
+                    f.kind = Some(kind);
+                    if let Some(unread) = unread {
+                        f.unread = unread;
+                    }
+                    f.last_seen = published;
+                });
+                // their unread counts only change with new articles
+                if new > 0 {
+                    worker.refresh_smart_folders();
+                    worker.refresh_tags();
+                }
+                fetched_feed(&mut conn, FetchState::Idle)?;
+
+                tracing::info!("{site}: fetched feeds {published}");
+
+                Ok(())
+            }
+            .await;
+            drop(permits);
+
+            if let Err(e) = &result {
+                tracing::error!("{name}: {e}");
+                let reason = e.to_string();
+                let status = e.status();
+                let retry_at = match e {
+                    FetchError::RetryAfter(_, d) => Some(
+                        chrono::Utc::now()
+                            .timestamp_millis()
+                            .saturating_add(d.as_millis().try_into().unwrap_or(i64::MAX)),
+                    ),
+                    _ => None,
+                };
+                let failed = worker
+                    .pool
+                    .get()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|mut conn| {
+                        db::update_feed_failed(
+                            &mut conn,
+                            feed_id,
+                            &reason,
+                            status,
+                            retry_at,
+                            worker.dead_after,
+                        )
+                    })
+                    .map_err(|e| tracing::error!("{name}: {e}"))
+                    .ok();
+                if let Some((failures, true)) = failed {
+                    worker.notify(Notification::error(format!(
+                        "{name} stopped refreshing after {failures} failures"
+                    )));
+                }
+                worker.with_feed(folder_id, feed_id, |f| {
+                    if let Some((failures, dead)) = failed {
+                        f.failures = failures;
+                        f.dead = dead;
+                    } else {
+                        f.failures += 1;
+                    }
+                    f.http_status = status.or(f.http_status);
+                    f.retry_at = retry_at;
+                    f.last_error = Some(reason.clone());
+                    f.state = FetchState::Failed {
+                        reason,
+                        at: chrono::Utc::now().timestamp_millis(),
+                    };
+                });
+            }
+            if tracked {
+                if let Ok(mut progress) = worker.progress.write() {
+                    progress.finish(result.is_ok());
+                }
+            }
+
+            result
+        });
+
+        Some(handle)
+    }
+
+    /// Refreshes every feed periodically, a feed has its own interval or follows the
+    /// global default, and requests are jittered so they dont fire at once.
+    pub async fn schedule(self) {
+        let Self { pool, folders, .. } = self.clone();
+        let mut due = HashMap::<u64, Instant>::new();
+        let mut ticker = tokio::time::interval(SCHEDULER_TICK);
+        let mut prune = Instant::now() + STARTUP_SPREAD;
+
+        loop {
+            ticker.tick().await;
+
+            if prune <= Instant::now() {
+                prune = Instant::now() + PRUNE_INTERVAL;
+                let worker = self.clone();
+                tokio::task::spawn(async move {
+                    match worker.prune().await {
+                        Ok(Pruned { articles: 0, .. }) => {}
+                        Ok(Pruned { articles, freed }) => {
+                            worker.notify(Notification::info(format!(
+                                "Pruned {articles} old articles, freed {}",
+                                utils::size(freed)
+                            )))
+                        }
+                        Err(e) => tracing::error!("prune: {e}"),
+                    }
+                });
+            }
+
+            let default_interval =
+                setting(&pool, db::REFRESH_INTERVAL).unwrap_or(DEFAULT_REFRESH_INTERVAL);
+
+            let now = Instant::now();
+            let millis = chrono::Utc::now().timestamp_millis();
+            let Ok(feeds) = folders.read().map(|folders| {
+                folders
+                    .iter()
+                    .filter_map(|f| f.feeds.as_ref())
+                    .flatten()
+                    // dead feeds wait for a review
+                    .filter(|f| !f.dead)
+                    // the server asked to come back later
+                    .filter(|f| !f.retry_at.is_some_and(|at| at > millis))
+                    .map(|f| {
+                        let interval = Duration::from_secs(
+                            f.interval
+                                .unwrap_or(default_interval)
+                                .max(MIN_REFRESH_INTERVAL),
+                        );
+                        // failing feeds are polled less often
+                        let factor = 2u32.saturating_pow(f.failures).min(MAX_BACKOFF_FACTOR);
+                        (f.id, f.folder_id, interval * factor)
+                    })
+                    .collect::<Vec<_>>()
+            }) else {
+                continue;
+            };
+
+            // forget deleted feeds
+            due.retain(|id, _| feeds.iter().any(|(feed_id, ..)| feed_id == id));
+
+            for (feed_id, folder_id, interval) in feeds {
+                let next = due
+                    .entry(feed_id)
+                    .or_insert_with(|| now + utils::jitter(interval.min(STARTUP_SPREAD)));
+                if *next > now {
+                    continue;
+                }
+                *next = now + interval + utils::jitter(interval / 10);
+
+                let worker = self.clone();
+                let folders = folders.clone();
+                tokio::task::spawn(async move {
+                    tokio::time::sleep(utils::jitter(SCHEDULER_TICK)).await;
+
+                    let feed = folders.read().ok().and_then(|folders| {
+                        folders
+                            .iter()
+                            .find(|f| f.id == folder_id)
+                            .and_then(|f| f.feeds.as_ref())
+                            .and_then(|feeds| feeds.iter().find(|f| f.id == feed_id))
+                            .cloned()
+                    });
+                    if let Some(feed) = feed {
+                        tracing::info!("{}: scheduled refresh", feed.name);
+                        // failures are kept by the fetch, the progress is
+                        // left to refreshes started by the user
+                        worker.fetch(feed, false);
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn setting<T: FromSql>(pool: &Pool<SqliteConnectionManager>, key: &str) -> Option<T> {
+    pool.get()
+        .ok()
+        .and_then(|mut conn| db::get_setting(&mut conn, key).ok())
+        .flatten()
+}
+
+/// Sends a request, network errors, `429` and `5xx` are retried with an
+/// exponential backoff, or after the `Retry-After` of the response.
+async fn send(
+    req: reqwest::RequestBuilder,
+    retries: usize,
+) -> Result<reqwest::Response, FetchError> {
+    use reqwest::{header, StatusCode};
+
+    let mut backoff = ExponentialBuilder::default()
+        .with_min_delay(RETRY_MIN_DELAY)
+        .with_max_delay(RETRY_MAX_DELAY)
+        .with_max_times(retries)
+        .with_jitter()
+        .build();
+    loop {
+        let Some(attempt) = req.try_clone() else {
+            return Ok(req.send().await?);
+        };
+        let (error, retry_after) = match attempt.send().await {
+            Ok(resp)
+                if resp.status() == StatusCode::TOO_MANY_REQUESTS
+                    || resp.status().is_server_error() =>
+            {
+                let retry_after = resp
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
+                (FetchError::Status(resp.status()), retry_after)
+            }
+            Ok(resp) => return Ok(resp),
+            Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => (e.into(), None),
+            Err(e) => return Err(e.into()),
+        };
+        // too long to wait here, the scheduler skips the feed until then
+        if let (FetchError::Status(status), Some(d)) = (&error, retry_after) {
+            if d > MAX_RETRY_AFTER {
+                return Err(FetchError::RetryAfter(*status, d));
+            }
+        }
+        let Some(delay) = backoff.next() else {
+            return Err(error);
+        };
+        let delay = retry_after.unwrap_or(delay);
+        tracing::info!("{error}, retry in {delay:?}");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Follows redirects by hand, the url of a permanent move is returned with the
+/// response when every redirect on the way is permanent.
+async fn follow(
+    request: impl Fn(url::Url) -> reqwest::RequestBuilder,
+    url: &str,
+    retries: usize,
+) -> Result<(reqwest::Response, Option<(String, u16)>), FetchError> {
+    use reqwest::{header, StatusCode};
+
+    let mut url = url::Url::parse(url)?;
+    let mut moved = None;
+    let mut permanent = true;
+    for _ in 0..=MAX_REDIRECTS {
+        let resp = send(request(url.clone()), retries).await?;
+        let status = resp.status();
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|v| v.to_str().ok());
+        let Some(location) = location.filter(|_| {
+            matches!(
+                status,
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::SEE_OTHER
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            )
+        }) else {
+            return Ok((resp, moved));
+        };
+        url = url.join(location)?;
+        permanent &= matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+        if permanent {
+            moved = Some((url.to_string(), status.as_u16()));
+        }
+    }
+    Err(FetchError::TooManyRedirects)
+}
+
+/// The subscriptions as an OPML document, and the number of feeds in it.
+pub fn export_opml(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+) -> Result<(usize, String), ExportError> {
+    let folders = db::fetch_folders(conn)?;
+    let feeds = folders
+        .iter()
+        .filter_map(|f| f.feeds.as_ref())
+        .map(Vec::len)
+        .sum();
+    Ok((feeds, opml::export(&folders)?))
+}
+
+/// Gets a url, a response which is not successful is an error.
+async fn get(url: &str, retries: usize) -> Result<reqwest::Response, FetchError> {
+    let (resp, _) = follow(|url| CLIENT.get(url), url, retries).await?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(FetchError::Status(status));
+    }
+    Ok(resp)
+}
+
+/// The mime type of a response, without parameters.
+fn content_type(resp: &reqwest::Response) -> Option<String> {
+    resp.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+}
+
+/// `Retry-After` is either seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    chrono::DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|t| (t.timestamp_millis() - chrono::Utc::now().timestamp_millis()).max(0))
+        .map(|ms| Duration::from_millis(ms as u64))
+}
+
+/// Limits the number of in-flight requests, in total and per host.
+#[derive(Clone)]
+struct Limits {
+    total: Arc<Semaphore>,
+    per_host: usize,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl Limits {
+    fn new(total: usize, per_host: usize) -> Self {
+        Self {
+            total: Arc::new(Semaphore::new(total.max(1))),
+            per_host: per_host.max(1),
+            hosts: Arc::default(),
+        }
+    }
+
+    async fn acquire(&self, url: &str) -> Option<(OwnedSemaphorePermit, OwnedSemaphorePermit)> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(ToOwned::to_owned))
+            .unwrap_or_default();
+        let host = self
+            .hosts
+            .lock()
+            .ok()?
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_host)))
+            .clone();
+        // host first, dont hold a global permit while waiting for a busy host
+        let host = host.acquire_owned().await.ok()?;
+        let total = self.total.clone().acquire_owned().await.ok()?;
+        Some((host, total))
+    }
+}
@@ -1,12 +1,26 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 use std::vec;
 
 use eframe::egui::{self, FontData, FontDefinitions, Label, RichText, Sense};
 use egui_extras::RetainedImage;
 
-use crate::*;
+use crate::{
+    errors::{FeedError, Gone},
+    service::{Command, Pending},
+    *,
+};
+
+/// how long a notification is shown
+const INFO_TIMEOUT: Duration = Duration::from_secs(4);
+const ERROR_TIMEOUT: Duration = Duration::from_secs(12);
+/// older notifications are dropped
+const MAX_NOTIFICATIONS: usize = 5;
+/// articles of a timeline loaded at once
+const TIMELINE_PAGE: usize = 50;
 
 pub struct App {
     icons: HashMap<&'static str, RetainedImage>,
@@ -18,7 +32,62 @@ pub struct App {
 
     feed: models::Feed,
 
+    /// the opened article with its content
     article: models::Article,
+    opening: Option<Pending<models::Article, FeedError>>,
+    /// the parsed content of the opened article
+    document: easymark::Document,
+    documents: easymark::Documents,
+    parsing: Option<Pending<easymark::Document, Gone>>,
+    /// why the opened article can't be shown
+    failed: Option<String>,
+    /// images of the opened article
+    images: easymark::Images,
+
+    notifications: Vec<(models::Notification, Instant)>,
+
+    /// hides feeds without unread articles in the sidebar
+    hide_read: bool,
+
+    timeline: models::Timeline,
+    /// loaded pages of the timeline
+    articles: Vec<models::ArticleSummary>,
+    /// a page is full, there could be more
+    more: bool,
+    loading: Option<Pending<Vec<models::ArticleSummary>, FeedError>>,
+    /// articles being loaded, a page starts after the loaded ones or replaces
+    /// them from `0`
+    page: Range<usize>,
+
+    search: String,
+    hits: Vec<search::Hit>,
+    searching: Option<Pending<Vec<search::Hit>, FeedError>>,
+
+    /// a new tag of the current article
+    tag: String,
+    /// changes of articles being stored, only their errors are shown
+    changes: Vec<Change>,
+}
+
+/// A change of articles made from the timeline or the reader.
+enum Change {
+    Read(Pending<usize, FeedError>),
+    Unread(Pending<(), FeedError>),
+    Star(Pending<Option<i64>, FeedError>),
+    Tags(Pending<Vec<String>, FeedError>),
+}
+
+impl Change {
+    /// `None` while the change is stored, its error is ready to be shown.
+    fn try_recv(&mut self) -> Option<Result<(), String>> {
+        let (result, action) = match self {
+            Self::Read(pending) => (pending.try_recv()?.map(drop), "Mark as read"),
+            Self::Unread(pending) => (pending.try_recv()?, "Mark as unread"),
+            Self::Star(pending) => (pending.try_recv()?.map(drop), "Star"),
+            Self::Tags(pending) => (pending.try_recv()?.map(drop), "Tagging"),
+        };
+        Some(result.map_err(|e| format!("{action} failed: {e}")))
+    }
 }
 
 impl App {
@@ -69,6 +138,13 @@ impl App {
             Box::new(windows::folder::AddWindow::default()),
             Box::new(windows::folder::DeleteWindow::default()),
             Box::new(windows::folder::EditWindow::default()),
+            Box::new(windows::opml::ImportWindow::default()),
+            Box::new(windows::opml::ExportWindow::default()),
+            Box::new(windows::smart::AddWindow::default()),
+            Box::new(windows::smart::EditWindow::default()),
+            Box::new(windows::smart::DeleteWindow::default()),
+            Box::new(windows::rules::RulesWindow::default()),
+            Box::new(windows::retention::RetentionWindow::default()),
         ];
         let open = HashMap::default();
 
@@ -100,6 +176,24 @@ impl App {
             open,
             feed: models::Feed::default(),
             article: models::Article::default(),
+            opening: None,
+            document: easymark::Document::default(),
+            documents: easymark::Documents::default(),
+            parsing: None,
+            failed: None,
+            images: easymark::Images::default(),
+            notifications: Vec::new(),
+            hide_read: false,
+            timeline: models::Timeline::default(),
+            articles: Vec::new(),
+            more: false,
+            loading: None,
+            page: 0..0,
+            search: String::new(),
+            hits: Vec::new(),
+            searching: None,
+            tag: String::new(),
+            changes: Vec::new(),
         }
     }
 
@@ -117,15 +211,229 @@ impl App {
             set_open(open, window.name(), is_open, None);
         }
     }
+
+    /// Imports an OPML file dropped onto the window.
+    pub fn dropped_files(&mut self, ctx: &egui::Context) {
+        let path = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .find(|p| {
+                    p.extension()
+                        .map(|e| e.eq_ignore_ascii_case("opml"))
+                        .unwrap_or_default()
+                })
+        });
+        if let Some(path) = path {
+            set_open(
+                &mut self.open,
+                windows::opml::ImportWindow::NAME,
+                true,
+                Some(Message::File(path)),
+            );
+        }
+    }
+
+    /// Receives the results of a search.
+    pub fn hits(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.searching.as_mut() else {
+            return;
+        };
+        let Some(result) = pending.try_recv() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        match result {
+            Ok(hits) => self.hits = hits,
+            Err(e) => self.error(format!("Search failed: {e}")),
+        }
+        self.searching = None;
+    }
+
+    /// Receives a page of the timeline, the first one replaces the others.
+    pub fn timeline(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.loading.as_mut() else {
+            return;
+        };
+        let Some(result) = pending.try_recv() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        match result {
+            Ok(articles) => {
+                if self.page.start == 0 {
+                    self.articles.clear();
+                }
+                self.more = articles.len() == self.page.len();
+                self.articles.extend(articles);
+            }
+            Err(e) => {
+                // the next page is not requested again and again
+                self.more = false;
+                self.error(format!("Loading articles failed: {e}"));
+            }
+        }
+        self.loading = None;
+    }
+
+    /// Receives the content of the opened article.
+    pub fn article(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.opening.as_mut() else {
+            return;
+        };
+        let Some(result) = pending.try_recv() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        match result {
+            // another one could be opened meanwhile
+            Ok(article) if article.id == self.article.id => {
+                match self.documents.get(article.id, &article.content) {
+                    Some(document) => self.document = document,
+                    None => {
+                        self.parsing = Some(easymark::Document::parse_in_background(
+                            article.id,
+                            article.content.clone(),
+                        ))
+                    }
+                }
+                self.images.open(&article.url);
+                self.article = article;
+            }
+            Ok(_) => {}
+            Err(e) => self.failed = Some(format!("Can't open the article: {e}")),
+        }
+        self.opening = None;
+    }
+
+    /// Receives the parsed content of an article, it is kept for the next
+    /// time the article is opened.
+    pub fn document(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.parsing.as_mut() else {
+            return;
+        };
+        let Some(result) = pending.try_recv() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        match result {
+            Ok(document) => {
+                if document.id == self.article.id {
+                    self.document = document.clone();
+                }
+                self.documents.insert(document);
+            }
+            // the opened article is still waiting for it
+            Err(e) if self.document.id != self.article.id => {
+                tracing::error!("{e}");
+                self.failed = Some("Can't show the article, parsing its content failed".into());
+            }
+            Err(e) => tracing::error!("{e}"),
+        }
+        self.parsing = None;
+    }
+
+    /// Receives the results of changes of articles, failed ones are
+    /// notified.
+    pub fn changes(&mut self, ctx: &egui::Context) {
+        let mut errors = Vec::new();
+        self.changes.retain_mut(|change| match change.try_recv() {
+            None => true,
+            Some(result) => {
+                errors.extend(result.err());
+                false
+            }
+        });
+        for error in errors {
+            self.error(error);
+        }
+        if !self.changes.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    /// Shows an error of the UI with the notifications of the worker.
+    fn error(&mut self, text: String) {
+        self.notifications
+            .push((models::Notification::error(text), Instant::now()));
+    }
+
+    /// Requests the images drawn in the reader and receives loaded ones.
+    pub fn images(&mut self, ctx: &egui::Context) {
+        if self.images.poll(&self.store) {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    /// Shows notifications pushed by the worker at the bottom right corner.
+    pub fn notifications(&mut self, ctx: &egui::Context) {
+        if let Ok(mut notifications) = self.store.notifications.try_write() {
+            let now = Instant::now();
+            self.notifications
+                .extend(notifications.drain(..).map(|n| (n, now)));
+        }
+        let len = self.notifications.len();
+        if len > MAX_NOTIFICATIONS {
+            self.notifications.drain(..len - MAX_NOTIFICATIONS);
+        }
+        self.notifications.retain(|(n, at)| {
+            at.elapsed()
+                < match n.level {
+                    models::Level::Info => INFO_TIMEOUT,
+                    models::Level::Error => ERROR_TIMEOUT,
+                }
+        });
+        if self.notifications.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new("Notifications")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+            .show(ctx, |ui| {
+                for (i, (notification, _)) in self.notifications.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            let color = match notification.level {
+                                models::Level::Info => ui.visuals().text_color(),
+                                models::Level::Error => ui.visuals().error_fg_color,
+                            };
+                            ui.colored_label(color, &notification.text);
+                            if ui.small_button("✖").clicked() {
+                                dismissed = Some(i);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(i) = dismissed {
+            self.notifications.remove(i);
+        }
+        ctx.request_repaint_after(Duration::from_millis(500));
+    }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.dropped_files(ctx);
         self.windows(ctx, frame.info().window_info.size);
+        self.notifications(ctx);
+        self.timeline(ctx);
+        self.article(ctx);
+        self.document(ctx);
+        self.hits(ctx);
+        self.changes(ctx);
 
-        let Store {
-            folders, sender, ..
-        } = &self.store;
+        let store = &self.store;
+        let changes = &mut self.changes;
+        let folders = &store.folders;
+        // articles of the timeline to load once the frame is drawn
+        let mut load: Option<Range<usize>> = None;
+        let mut selected: Option<models::Timeline> = None;
+        // the article opened in the list
+        let mut opened = self.article.id;
 
         egui::TopBottomPanel::top("Navbar")
             // .exact_height(38.)
@@ -169,7 +477,79 @@ impl eframe::App for App {
                                 Some(Message::Normal),
                             );
                         }
+                        if ui
+                            .add(egui::Button::image_and_text(
+                                img.texture_id(ctx),
+                                img.size_vec2() * 0.5,
+                                "Smart Folder",
+                            ))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            set_open(
+                                &mut self.open,
+                                windows::smart::AddWindow::NAME,
+                                true,
+                                Some(Message::Normal),
+                            );
+                        }
+                        ui.separator();
+                        if ui.button("Rules").clicked() {
+                            ui.close_menu();
+                            set_open(
+                                &mut self.open,
+                                windows::rules::RulesWindow::NAME,
+                                true,
+                                Some(Message::Normal),
+                            );
+                        }
+                        if ui.button("Retention").clicked() {
+                            ui.close_menu();
+                            set_open(
+                                &mut self.open,
+                                windows::retention::RetentionWindow::NAME,
+                                true,
+                                Some(Message::Normal),
+                            );
+                        }
+                        if ui.button("Import OPML").clicked() {
+                            ui.close_menu();
+                            set_open(
+                                &mut self.open,
+                                windows::opml::ImportWindow::NAME,
+                                true,
+                                Some(Message::Normal),
+                            );
+                        }
+                        if ui.button("Export OPML").clicked() {
+                            ui.close_menu();
+                            set_open(
+                                &mut self.open,
+                                windows::opml::ExportWindow::NAME,
+                                true,
+                                Some(Message::Normal),
+                            );
+                        }
                     });
+
+                    let progress = store.progress.read().map(|p| *p).unwrap_or_default();
+                    if ui
+                        .add_enabled(!progress.is_active(), egui::Button::new("🔄"))
+                        .on_hover_text("Refresh all")
+                        .clicked()
+                    {
+                        store.send(Command::FetchAll);
+                    }
+                    if progress.is_active() {
+                        ui.spinner();
+                        ui.label(format!("{}/{}", progress.done, progress.total));
+                        ctx.request_repaint_after(Duration::from_millis(200));
+                    } else if progress.failed > 0 {
+                        ui.label(
+                            RichText::new(format!("{} failed", progress.failed))
+                                .color(ui.visuals().warn_fg_color),
+                        );
+                    }
                 });
             });
 
@@ -182,7 +562,27 @@ impl eframe::App for App {
                     let img = self.icons.get("rss").unwrap();
                     ui.image(img.texture_id(ctx), img.size_vec2());
                     ui.heading("Feeds");
+                    let unread = folders
+                        .try_read()
+                        .map(|folders| folders.iter().map(models::Folder::unread).sum::<usize>())
+                        .unwrap_or_default();
+                    unread_badge(ui, unread);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.toggle_value(&mut self.hide_read, "Unread")
+                            .on_hover_text("Hide feeds with no unread articles");
+                    });
                 });
+                let resp = ui.add(
+                    egui::TextEdit::singleline(&mut self.search)
+                        .hint_text("Search")
+                        .desired_width(f32::INFINITY),
+                );
+                if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    selected = Some(models::Timeline::Search);
+                }
+                resp.on_hover_text(
+                    "Words, \"phrases\" and prefix*\nfeed:, folder:, author:, tag: and is:unread",
+                );
                 ui.separator();
 
                 egui::ScrollArea::vertical()
@@ -195,9 +595,77 @@ impl eframe::App for App {
                         let link_img = self.icons.get("link").unwrap();
                         let open = &mut self.open;
                         let current_feed = &mut self.feed;
-                        let current_article = &mut self.article;
+                        let hide_read = self.hide_read;
+                        let timeline = &mut self.timeline;
+                        let selected = &mut selected;
+
+                        for (view, label) in [
+                            (models::Timeline::All, "All articles"),
+                            (models::Timeline::Unread, "Unread"),
+                            (models::Timeline::Today, "Today"),
+                            (models::Timeline::Starred, "★ Starred"),
+                        ] {
+                            // selecting it again reloads it
+                            if ui.selectable_label(*timeline == view, label).clicked() {
+                                *selected = Some(view);
+                            }
+                        }
+                        if let Ok(smart_folders) = store.smart_folders.try_read() {
+                            for folder in smart_folders.iter() {
+                                let view = models::Timeline::Smart(folder.id);
+                                ui.horizontal(|ui| {
+                                    let resp = ui.selectable_label(*timeline == view, &folder.name);
+                                    unread_badge(ui, folder.unread);
+                                    if resp.clicked() {
+                                        *selected = Some(view);
+                                    }
+                                    resp.context_menu(|ui| {
+                                        for (label, name) in [
+                                            ("Edit", windows::smart::EditWindow::NAME),
+                                            ("Delete", windows::smart::DeleteWindow::NAME),
+                                        ] {
+                                            if ui.button(label).clicked() {
+                                                ui.close_menu();
+                                                set_open(
+                                                    open,
+                                                    name,
+                                                    true,
+                                                    Some(Message::SmartFolder(
+                                                        Action::Update,
+                                                        folder.clone(),
+                                                    )),
+                                                );
+                                            }
+                                        }
+                                    });
+                                });
+                            }
+                        }
+                        if let Ok(tags) = store.tags.try_read() {
+                            if !tags.is_empty() {
+                                egui::CollapsingHeader::new("🏷 Tags")
+                                    .id_source("tags")
+                                    .default_open(true)
+                                    .show(ui, |ui| {
+                                        for tag in tags.iter() {
+                                            let view = models::Timeline::Tag(tag.id);
+                                            ui.horizontal(|ui| {
+                                                if ui
+                                                    .selectable_label(*timeline == view, &tag.name)
+                                                    .clicked()
+                                                {
+                                                    *selected = Some(view);
+                                                }
+                                                unread_badge(ui, tag.unread);
+                                            });
+                                        }
+                                    });
+                            }
+                        }
+                        ui.separator();
 
                         if let Ok(folders) = folders.try_read() {
+                            let changes = &mut *changes;
                             folders.iter().for_each(move |folder| {
 
                                 let id = ui.make_persistent_id(folder.name.to_string());
@@ -207,7 +675,9 @@ impl eframe::App for App {
                                     false,
                                 )
                                 .show_header(ui, |ui| {
-                                    ui.label(folder.name.to_string()).context_menu(|ui| {
+                                    let resp = ui.label(folder.name.to_string());
+                                    unread_badge(ui, folder.unread());
+                                    resp.context_menu(|ui| {
                                         ui.horizontal(|ui| {
                                             ui.image(
                                                 folder_img.texture_id(ctx),
@@ -216,7 +686,25 @@ impl eframe::App for App {
                                             ui.label(folder.name.to_string());
                                         });
                                         ui.separator();
-                                        ui.button("Mark as read");
+                                        if ui.button("All in folder").clicked() {
+                                            ui.close_menu();
+                                            *selected = Some(models::Timeline::Folder(folder.id));
+                                        }
+                                        mark_read(
+                                            ui,
+                                            store,
+                                            changes,
+                                            models::Scope::Folder(folder.id),
+                                        );
+                                        if ui.button("Refresh").clicked() {
+                                            ui.close_menu();
+                                            store.send(|reply| {
+                                                Command::FetchFolder(
+                                                    folder.clone_without_feeds(),
+                                                    reply,
+                                                )
+                                            });
+                                        }
                                         ui.separator();
                                         if ui.button("Rename").clicked() {
                                             ui.close_menu();
@@ -230,19 +718,17 @@ impl eframe::App for App {
                                                 )),
                                             );
                                         }
-                                        if folder.id > 1 {
-                                            if ui.button("Delete").clicked() {
-                                                ui.close_menu();
-                                                set_open(
-                                                    open,
-                                                    windows::folder::DeleteWindow::NAME,
-                                                    true,
-                                                    Some(Message::Folder(
-                                                        Action::Delete,
-                                                        folder.clone_without_feeds()
-                                                    )),
-                                                );
-                                            }
+                                        if folder.id > 1 && ui.button("Delete").clicked() {
+                                            ui.close_menu();
+                                            set_open(
+                                                open,
+                                                windows::folder::DeleteWindow::NAME,
+                                                true,
+                                                Some(Message::Folder(
+                                                    Action::Delete,
+                                                    folder.clone_without_feeds()
+                                                )),
+                                            );
                                         }
                                     });
                                 })
@@ -251,13 +737,23 @@ impl eframe::App for App {
                                         egui::Layout::top_down_justified(egui::Align::LEFT),
                                         |ui| {
                                             if let Some(feeds) = &folder.feeds{
-                                                feeds.iter().for_each(|feed| {
-                                                    if ui
-                                                        .selectable_value(
-                                                            &mut current_feed.id,
-                                                            feed.id,
-                                                            feed.name.to_string(),
-                                                        )
+                                                let current_id = current_feed.id;
+                                                feeds
+                                                    .iter()
+                                                    // keeps the current feed while it is read
+                                                    .filter(|feed| {
+                                                        !hide_read
+                                                            || feed.unread > 0
+                                                            || feed.id == current_id
+                                                    })
+                                                    .for_each(|feed| {
+                                                    let resp = ui.selectable_value(
+                                                        &mut current_feed.id,
+                                                        feed.id,
+                                                        feed.name.to_string(),
+                                                    );
+                                                    let resp = feed_state(ui, resp, feed);
+                                                    if resp
                                                         .context_menu(|ui| {
                                                             ui.horizontal(|ui| {
                                                                 ui.image(
@@ -267,7 +763,21 @@ impl eframe::App for App {
                                                                 ui.label(feed.name.to_string());
                                                             });
                                                             ui.separator();
-                                                            ui.button("Mark as read");
+                                                            mark_read(
+                                                                ui,
+                                                                store,
+                                                                changes,
+                                                                models::Scope::Feed(feed.id),
+                                                            );
+                                                            if ui.button("Refresh").clicked() {
+                                                                ui.close_menu();
+                                                                store.send(|reply| {
+                                                                    Command::FetchFeed(
+                                                                        feed.clone(),
+                                                                        reply,
+                                                                    )
+                                                                });
+                                                            }
                                                             ui.separator();
                                                             if ui.button("Edit").clicked() {
                                                                 ui.close_menu();
@@ -296,14 +806,13 @@ impl eframe::App for App {
                                                         })
                                                         .changed()
                                                     {
-                                                        *current_article = models::Article::default();
+                                                        *selected = Some(models::Timeline::Feed(
+                                                            feed.id,
+                                                        ));
                                                         *current_feed = feed.clone();
-                                                        if let Err(e) = sender.send(Message::Feed (
-                                                            Action::Fetch,
-                                                            feed.clone_with_last_article(),
-                                                        )) {
-                                                            tracing::error!("{e}");
-                                                        }
+                                                        store.send(|reply| {
+                                                            Command::FetchFeed(feed.clone(), reply)
+                                                        });
                                                     }
                                                 });
                                             }
@@ -315,12 +824,56 @@ impl eframe::App for App {
                     });
             });
 
+        if let Some(view) = selected {
+            if !matches!(view, models::Timeline::Feed(_)) {
+                self.feed = models::Feed::default();
+            }
+            self.timeline = view;
+            self.article = models::Article::default();
+            self.articles.clear();
+            opened = 0;
+            load = Some(0..TIMELINE_PAGE);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
-                let mut name = self.feed.name.to_string();
-                if name.is_empty() {
-                    name.push_str("Feeds");
-                }
+                let name = match self.timeline {
+                    models::Timeline::Feed(_) if self.feed.name.is_empty() => "Feeds".to_owned(),
+                    models::Timeline::Feed(_) => self.feed.name.to_string(),
+                    models::Timeline::All => "All articles".to_owned(),
+                    models::Timeline::Unread => "Unread".to_owned(),
+                    models::Timeline::Today => "Today".to_owned(),
+                    models::Timeline::Starred => "Starred".to_owned(),
+                    models::Timeline::Search => format!("Search: {}", self.search.trim()),
+                    models::Timeline::Smart(id) => store
+                        .smart_folders
+                        .try_read()
+                        .ok()
+                        .and_then(|folders| {
+                            folders.iter().find(|f| f.id == id).map(|f| f.name.clone())
+                        })
+                        .unwrap_or_default(),
+                    models::Timeline::Tag(id) => store
+                        .tags
+                        .try_read()
+                        .ok()
+                        .and_then(|tags| {
+                            tags.iter()
+                                .find(|t| t.id == id)
+                                .map(|t| format!("🏷 {}", t.name))
+                        })
+                        .unwrap_or_default(),
+                    models::Timeline::Folder(id) => folders
+                        .try_read()
+                        .ok()
+                        .and_then(|folders| {
+                            folders
+                                .iter()
+                                .find(|f| f.id == id)
+                                .map(|f| f.name.to_string())
+                        })
+                        .unwrap_or_default(),
+                };
                 let link_img = self.icons.get("link").unwrap();
                 ui.image(link_img.texture_id(ctx), link_img.size_vec2() * 0.5);
                 if ui
@@ -334,6 +887,21 @@ impl eframe::App for App {
                         .and_then(|site| open::that(site).ok());
                 }
             });
+            let moved = folders.try_read().ok().and_then(|folders| {
+                folders
+                    .iter()
+                    .find(|folder| folder.id == self.feed.folder_id)
+                    .and_then(|folder| folder.feeds.as_ref())
+                    .and_then(|feeds| feeds.iter().find(|feed| feed.id == self.feed.id))
+                    .and_then(|feed| Some((feed.moved_from.clone()?, feed.url.clone())))
+            });
+            if let Some((from, to)) = moved {
+                ui.label(
+                    RichText::new(format!("This feed moved from {from} to {to}"))
+                        .small()
+                        .weak(),
+                );
+            }
 
             ui.separator();
 
@@ -342,78 +910,369 @@ impl eframe::App for App {
                 .default_width(280.)
                 .width_range(128.0..=360.)
                 .show_inside(ui, |ui| {
+                    let Ok(folders) = folders.try_read() else {
+                        return;
+                    };
+                    let opened = &mut opened;
+                    // the feed is in the heading
+                    let feed = matches!(self.timeline, models::Timeline::Feed(_));
+                    if self.timeline == models::Timeline::Search {
+                        egui::ScrollArea::vertical()
+                            .auto_shrink([false; 2])
+                            .show(ui, |ui| {
+                                ui.with_layout(
+                                    egui::Layout::top_down_justified(egui::Align::LEFT),
+                                    |ui| {
+                                        for hit in &self.hits {
+                                            let article = &hit.article;
+                                            let source = feed_name(&folders, article.feed_id);
+                                            if article_row(
+                                                ui, store, changes, opened, article, source,
+                                            ) {
+                                                load = Some(0..0);
+                                            }
+                                            if !hit.snippet.is_empty() {
+                                                snippet_label(ui, &hit.snippet);
+                                            }
+                                            ui.separator();
+                                        }
+                                        if self.hits.is_empty() && self.searching.is_none() {
+                                            ui.weak("No articles found");
+                                        }
+                                    },
+                                );
+                            });
+                        return;
+                    }
+
+                    // only the visible rows are drawn, rows have the same
+                    // height
+                    let row_height = ui.spacing().interact_size.y
+                        + ui.spacing().item_spacing.y
+                        + ui.text_style_height(&egui::TextStyle::Small);
+                    let len = self.articles.len();
                     egui::ScrollArea::vertical()
                         .auto_shrink([false; 2])
-                        .show_viewport(ui, |ui, rect| {
-                            ui.set_width(rect.width());
-                            ui.set_min_height(rect.height());
-
-                            ui.with_layout(
-                                egui::Layout::top_down_justified(egui::Align::LEFT),
-                                |ui| {
-                                    let current_article = &mut self.article;
-                                    let models::Feed { id, folder_id, .. } = self.feed;
-                                    if let Ok(folders) = folders.try_read() {
-                                        folders
-                                            .iter()
-                                            .find(|folder| folder.id == folder_id)
-                                            .and_then(|folder| folder.feeds.as_ref())
-                                            .and_then(|feeds| {
-                                                feeds.iter().find(|feed| feed.id == id)
-                                            })
-                                            .and_then(|feed| feed.articles.as_ref())
-                                            .map(|articles| {
-                                                articles.iter().rev().for_each(|article| {
-                                                    if ui
-                                                        .selectable_value(
-                                                            &mut current_article.id,
-                                                            article.id,
-                                                            article.title.to_string(),
-                                                        )
-                                                        .changed()
-                                                    {
-                                                        *current_article = article.clone();
-                                                    }
-
-                                                    ui.separator();
-                                                })
-                                            });
-                                    }
-                                },
-                            );
+                        .show_rows(ui, row_height, len, |ui, rows| {
+                            ui.style_mut().wrap = Some(false);
+                            let end = rows.end;
+                            for article in &self.articles[rows] {
+                                let source = (!feed)
+                                    .then(|| feed_name(&folders, article.feed_id))
+                                    .flatten();
+                                ui.allocate_ui_with_layout(
+                                    egui::vec2(ui.available_width(), row_height),
+                                    egui::Layout::top_down_justified(egui::Align::LEFT),
+                                    |ui| {
+                                        ui.set_height(row_height);
+                                        if article_row(ui, store, changes, opened, article, source)
+                                        {
+                                            load = Some(reload(len));
+                                        }
+                                    },
+                                );
+                            }
+                            // the last loaded one is shown
+                            if end == len && self.more && self.loading.is_none() {
+                                load = Some(len..len + TIMELINE_PAGE);
+                            }
                         });
+                    if len == 0 && self.loading.is_none() {
+                        ui.weak("No articles");
+                    }
                 });
 
             egui::CentralPanel::default().show_inside(ui, |ui| {
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    let mut events = Vec::new();
-                    easymark::parser(
-                        &htmlize::unescape(self.article.content.to_owned()),
-                        &mut events,
-                    );
-                    // easymark::parser(include_str!("../tests/fixtures/simple.html"), &mut events);
-                    // easymark::parser(
-                    //     include_str!("../tests/fixtures/blockquote.html"),
-                    //     &mut events,
-                    // );
-                    // easymark::parser(include_str!("../tests/fixtures/list.html"), &mut events);
-                    // easymark::parser(include_str!("../tests/fixtures/code.html"), &mut events);
-                    // easymark::parser(include_str!("../tests/fixtures/h_list.html"), &mut events);
-                    // easymark::parser(
-                    //     include_str!("../tests/fixtures/haskellweekly.html"),
-                    //     &mut events,
-                    // );
-                    // easymark::parser(
-                    //     &htmlize::unescape(include_str!(
-                    //         "../tests/fixtures/blog.rust-lang.org.raw"
-                    //     )),
-                    //     &mut events,
-                    // );
-                    easymark::render(ui, events);
-                });
+                if let Some(error) = &self.failed {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                } else if self.opening.is_some() {
+                    ui.spinner();
+                } else if self.article.id > 0 {
+                    let mut tags = None;
+                    ui.horizontal_wrapped(|ui| {
+                        let starred = self.article.starred.is_some();
+                        let label = if starred { "★ Unstar" } else { "☆ Star" };
+                        if ui.button(label).clicked() {
+                            let id = self.article.id;
+                            changes.push(Change::Star(
+                                store.send(|reply| Command::Star(id, !starred, reply)),
+                            ));
+                            self.article.starred =
+                                (!starred).then(|| chrono::Utc::now().timestamp_millis());
+                            load = Some(reload(self.articles.len()));
+                        }
+                        ui.separator();
+                        for (i, tag) in self.article.tags.iter().enumerate() {
+                            if ui
+                                .small_button(format!("🏷 {tag} ✖"))
+                                .on_hover_text("Remove tag")
+                                .clicked()
+                            {
+                                let mut rest = self.article.tags.clone();
+                                rest.remove(i);
+                                tags = Some(rest);
+                            }
+                        }
+                        let resp = ui.add(
+                            egui::TextEdit::singleline(&mut self.tag)
+                                .hint_text("Add tag")
+                                .desired_width(100.0),
+                        );
+                        if resp.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                            && !self.tag.trim().is_empty()
+                        {
+                            let tag = std::mem::take(&mut self.tag);
+                            let tag = tag.trim();
+                            if !self
+                                .article
+                                .tags
+                                .iter()
+                                .any(|t| t.eq_ignore_ascii_case(tag))
+                            {
+                                let mut more = self.article.tags.clone();
+                                more.push(tag.to_owned());
+                                tags = Some(more);
+                            }
+                        }
+                    });
+                    if let Some(tags) = tags {
+                        let id = self.article.id;
+                        self.article.tags = tags.clone();
+                        changes.push(Change::Tags(
+                            store.send(|reply| Command::SetTags(id, tags, reply)),
+                        ));
+                        load = Some(reload(self.articles.len()));
+                    }
+                    ui.separator();
+                }
+                if self.parsing.is_some() {
+                    ui.spinner();
+                } else if self.document.id == self.article.id {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        easymark::render(ui, &self.document.blocks, &mut self.images);
+                    });
+                }
             });
         });
+
+        // images drawn for the first time are requested right away
+        self.images(ctx);
+
+        if opened != self.article.id {
+            self.article = models::Article {
+                id: opened,
+                ..Default::default()
+            };
+            self.failed = None;
+            self.opening = Some(self.store.send(|reply| Command::Article(opened, reply)));
+        }
+
+        // new articles of the feed are fetched
+        if let models::Timeline::Feed(id) = self.timeline {
+            let last_seen = self.store.folders.try_read().ok().and_then(|folders| {
+                folders
+                    .iter()
+                    .filter_map(|folder| folder.feeds.as_ref())
+                    .flatten()
+                    .find(|feed| feed.id == id)
+                    .map(|feed| feed.last_seen)
+            });
+            if let Some(last_seen) = last_seen.filter(|t| *t != self.feed.last_seen) {
+                self.feed.last_seen = last_seen;
+                load.get_or_insert_with(|| reload(self.articles.len()));
+            }
+        }
+
+        match (self.timeline, load) {
+            (_, None) => {}
+            (models::Timeline::Search, Some(_)) => {
+                let query = self.search.clone();
+                self.searching = Some(self.store.send(|reply| Command::Search(query, reply)));
+            }
+            (timeline, Some(page)) => {
+                let after = match page.start {
+                    0 => None,
+                    _ => self.articles.last().map(|a| a.cursor(timeline)),
+                };
+                let limit = page.len();
+                self.loading = Some(
+                    self.store
+                        .send(|reply| Command::Timeline(timeline, after, limit, reply)),
+                );
+                self.page = page;
+            }
+        }
+    }
+}
+
+fn feed_name(folders: &[models::Folder], id: u64) -> Option<&str> {
+    folders
+        .iter()
+        .filter_map(|folder| folder.feeds.as_ref())
+        .flatten()
+        .find(|feed| feed.id == id)
+        .map(|feed| feed.name.as_str())
+}
+
+/// Loads the pages of a timeline again after a change.
+fn reload(loaded: usize) -> Range<usize> {
+    0..loaded.max(TIMELINE_PAGE)
+}
+
+/// Draws an article of a list with its context menu, `opened` is the id of
+/// the opened one. Returns true when the article is read, unread, starred or
+/// unstarred.
+fn article_row(
+    ui: &mut egui::Ui,
+    store: &Store,
+    changes: &mut Vec<Change>,
+    opened: &mut u64,
+    article: &models::ArticleSummary,
+    source: Option<&str>,
+) -> bool {
+    let mut title = String::new();
+    if article.starred.is_some() {
+        title.push_str("★ ");
+    }
+    title.push_str(&article.title);
+    let mut title = RichText::new(title);
+    if !article.read {
+        title = title.strong();
+    }
+    let mut changed = false;
+    let resp = ui.selectable_value(opened, article.id, title);
+    if resp.changed() && !article.read {
+        changed = true;
+        changes.push(Change::Read(store.send(|reply| {
+            Command::MarkRead(models::Scope::Article(article.id), None, reply)
+        })));
+    }
+    resp.context_menu(|ui| {
+        if article.read {
+            if ui.button("Mark as unread").clicked() {
+                ui.close_menu();
+                changed = true;
+                changes.push(Change::Unread(
+                    store.send(|reply| Command::MarkUnread(article.clone(), reply)),
+                ));
+            }
+        } else {
+            changed |= mark_read(ui, store, changes, models::Scope::Article(article.id));
+        }
+        let starred = article.starred.is_some();
+        if ui.button(if starred { "Unstar" } else { "Star" }).clicked() {
+            ui.close_menu();
+            changed = true;
+            changes.push(Change::Star(
+                store.send(|reply| Command::Star(article.id, !starred, reply)),
+            ));
+        }
+    });
+    let now = chrono::Utc::now().timestamp_millis();
+    let ago = utils::ago(now - article.created.max(article.updated));
+    let meta = match source {
+        Some(source) => format!("{source} · {ago}"),
+        None => ago,
+    };
+    ui.label(RichText::new(meta).small().weak());
+    changed
+}
+
+/// Draws a search snippet with its matched words highlighted.
+fn snippet_label(ui: &mut egui::Ui, snippet: &str) {
+    let font_id = egui::TextStyle::Small.resolve(ui.style());
+    let mut job = egui::text::LayoutJob::default();
+    for (part, matched) in search::highlights(snippet) {
+        let format = if matched {
+            egui::TextFormat {
+                font_id: font_id.clone(),
+                color: ui.visuals().strong_text_color(),
+                background: ui.visuals().selection.bg_fill,
+                ..Default::default()
+            }
+        } else {
+            egui::TextFormat {
+                font_id: font_id.clone(),
+                color: ui.visuals().weak_text_color(),
+                ..Default::default()
+            }
+        };
+        job.append(part, 0.0, format);
     }
+    ui.label(job);
+}
+
+/// Draws a spinner, an error badge or a stale indicator over the right side
+/// of a feed row.
+fn feed_state(ui: &mut egui::Ui, resp: egui::Response, feed: &models::Feed) -> egui::Response {
+    let mut badge = ui.child_ui(
+        resp.rect.shrink(2.0),
+        egui::Layout::right_to_left(egui::Align::Center),
+    );
+    if feed.unread > 0 {
+        badge.label(RichText::new(feed.unread.to_string()).small().strong());
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    if feed.state.is_busy() {
+        badge.add(egui::Spinner::new().size(resp.rect.height() - 4.0));
+        resp.on_hover_text(format!("{:?}", feed.state))
+    } else if feed.dead {
+        badge.colored_label(ui.visuals().error_fg_color, "⛔");
+        resp.on_hover_text(format!(
+            "stopped refreshing after {} failures, refresh to retry\n{}",
+            feed.failures,
+            feed.last_error.as_deref().unwrap_or_default()
+        ))
+    } else if let Some(error) = &feed.last_error {
+        badge.colored_label(ui.visuals().warn_fg_color, "⚠");
+        resp.on_hover_text(format!("{error}\nfailed {} times in a row", feed.failures))
+    } else if let Some(last_fetched) = feed.last_fetched.filter(|_| feed.is_stale(now)) {
+        badge.weak("⏳");
+        resp.on_hover_text(format!("updated {}", utils::ago(now - last_fetched)))
+    } else {
+        resp
+    }
+}
+
+fn unread_badge(ui: &mut egui::Ui, unread: usize) {
+    if unread > 0 {
+        ui.label(RichText::new(unread.to_string()).small().weak());
+    }
+}
+
+/// "Mark as read" items of a context menu, only articles can't be older.
+/// Returns true when one is clicked.
+fn mark_read(
+    ui: &mut egui::Ui,
+    store: &Store,
+    changes: &mut Vec<Change>,
+    scope: models::Scope,
+) -> bool {
+    let mut clicked = false;
+    if ui.button("Mark as read").clicked() {
+        ui.close_menu();
+        clicked = true;
+        changes.push(Change::Read(
+            store.send(|reply| Command::MarkRead(scope, None, reply)),
+        ));
+    }
+    if let models::Scope::Article(_) = scope {
+        return clicked;
+    }
+    ui.menu_button("Mark older than", |ui| {
+        for (label, days) in [("1 day", 1), ("1 week", 7), ("1 month", 30)] {
+            if ui.button(label).clicked() {
+                ui.close_menu();
+                let before = chrono::Utc::now() - chrono::Duration::days(days);
+                clicked = true;
+                changes.push(Change::Read(store.send(|reply| {
+                    Command::MarkRead(scope, Some(before.timestamp_millis()), reply)
+                })));
+            }
+        }
+    });
+    clicked
 }
 
 fn set_open(
@@ -1,3 +1,9 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
 pub fn extract_site_url(feed_url: String, links: Vec<feed_rs::model::Link>) -> String {
     let link = links.iter().find_map(|link| {
         if link
@@ -12,22 +18,68 @@ pub fn extract_site_url(feed_url: String, links: Vec<feed_rs::model::Link>) -> S
         }
     });
 
-    if let Some(link) = link.filter(|link| !is_feed_url(link)) {
+    if let Some(link) = link.filter(|link| !is_feed_url(&link.href)) {
         return trim_end(link.href.to_owned());
     }
 
     trim_end(feed_url)
 }
 
-fn is_feed_url(link: &feed_rs::model::Link) -> bool {
-    link.href.ends_with(".xml")
-        || link.href.ends_with(".atom")
-        || link.href.ends_with("rss/")
-        || link.href.ends_with("rss")
-        || link.href.ends_with("atom/")
-        || link.href.ends_with("atom")
-        || link.href.ends_with("feed")
-        || link.href.ends_with("feed/")
+/// Looks like a feed by its path.
+pub fn is_feed_url(href: &str) -> bool {
+    href.ends_with(".xml")
+        || href.ends_with(".atom")
+        || href.ends_with("rss/")
+        || href.ends_with("rss")
+        || href.ends_with("atom/")
+        || href.ends_with("atom")
+        || href.ends_with("feed")
+        || href.ends_with("feed/")
+}
+
+/// A random duration in `[0, max)`, spreads out requests.
+pub fn jitter(max: Duration) -> Duration {
+    let n = RandomState::new().build_hasher().finish();
+    max.mul_f64((n % 10_000) as f64 / 10_000.0)
+}
+
+/// A rough human readable duration, `ms` in milliseconds.
+pub fn ago(ms: i64) -> String {
+    let minutes = ms / 60_000;
+    match minutes {
+        i64::MIN..=0 => "just now".to_owned(),
+        1..=59 => format!("{minutes} min ago"),
+        60..=1439 => format!("{} h ago", minutes / 60),
+        _ => format!("{} days ago", minutes / 1440),
+    }
+}
+
+/// A human readable size, `bytes` in bytes.
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Text of an html fragment with its entities decoded, words of different
+/// elements are separated by a space.
+pub fn strip_html(html: &str) -> String {
+    let html = htmlize::unescape(html);
+    scraper::Html::parse_fragment(&html)
+        .root_element()
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 fn trim_end(url: String) -> String {
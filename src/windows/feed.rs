@@ -1,19 +1,19 @@
-use std::{
-    mem,
-    ops::{Div, Sub},
-};
+use std::ops::{Div, Sub};
 
 use eframe::{egui, emath};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    models::{Feed, Folder},
-    Action, Message, Store,
+    discovery::Candidate,
+    errors::{FeedError, FetchError},
+    models::{Feed, FeedPreview, FeedSettings, Folder},
+    service::{Command, Pending},
+    Message, Store,
 };
 
 use super::{View, Window};
 
-#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct AddWindow {
     url: String,
     name: String,
@@ -21,10 +21,71 @@ pub struct AddWindow {
     closed: bool,
     autofocus: bool,
     folders: Option<Vec<Folder>>,
+    /// feeds found on the page of `url`
+    candidates: Vec<Candidate>,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<Feed, FeedError>>,
+    #[serde(skip)]
+    discovering: Option<Pending<Vec<Candidate>, FetchError>>,
+    /// the parsed feed of `url`
+    #[serde(skip)]
+    preview: Option<FeedPreview>,
+    #[serde(skip)]
+    previewing: Option<Pending<FeedPreview, FetchError>>,
 }
 
 impl AddWindow {
     pub const NAME: &'static str = "Add Feed";
+
+    /// Description of a preview is cut to this many chars.
+    const DESCRIPTION_LEN: usize = 200;
+
+    fn pick(&mut self, candidate: Candidate) {
+        if self.url != candidate.url {
+            self.preview = None;
+        }
+        self.url = candidate.url;
+        if self.name.is_empty() {
+            self.name = candidate.title.unwrap_or_default();
+        }
+    }
+
+    fn preview_ui(ui: &mut egui::Ui, preview: &FeedPreview) {
+        ui.horizontal_top(|ui| {
+            ui.add_sized((50., 24.), egui::Label::new("Preview:"));
+            ui.vertical(|ui| {
+                ui.strong(preview.title.as_deref().unwrap_or("Untitled"));
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{:?}, {} entries",
+                        preview.feed_type, preview.entries
+                    ))
+                    .small()
+                    .weak(),
+                );
+                if let Some(description) = preview
+                    .description
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                {
+                    let mut text = description
+                        .chars()
+                        .take(Self::DESCRIPTION_LEN)
+                        .collect::<String>();
+                    if text.len() < description.len() {
+                        text.push('…');
+                    }
+                    ui.label(text);
+                }
+                for title in &preview.latest {
+                    ui.label(format!("• {title}"));
+                }
+            });
+        });
+        ui.end_row();
+    }
 }
 
 impl Window for AddWindow {
@@ -43,6 +104,8 @@ impl Window for AddWindow {
         // must
         if let Some(Message::RefreshFolders) = data.take() {
             self.autofocus = true;
+            self.error = None;
+            self.candidates.clear();
             if let Ok(reader) = store.folders.read() {
                 self.folder = reader[0].clone_without_feeds();
                 self.folders = Some(reader.to_vec());
@@ -64,23 +127,115 @@ impl Window for AddWindow {
 
 impl View for AddWindow {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.url.clear();
+                    self.name.clear();
+                    self.candidates.clear();
+                    self.preview = None;
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.discovering) {
+            match result {
+                Ok(candidates) if candidates.is_empty() => {
+                    self.error = Some("No feeds found".to_owned());
+                }
+                Ok(candidates) => {
+                    if let [candidate] = &candidates[..] {
+                        self.pick(candidate.clone());
+                    }
+                    self.candidates = candidates;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.previewing) {
+            match result {
+                Ok(preview) => {
+                    if self.name.is_empty() {
+                        self.name = preview.title.clone().unwrap_or_default();
+                    }
+                    self.preview = Some(preview);
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.add_sized((50., 24.), egui::Label::new("URL:"));
-            let resp =
-                ui.add(egui::TextEdit::singleline(&mut self.url).hint_text("Write feed url"));
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.url).hint_text("Write feed or website url"),
+            );
             if self.autofocus {
                 self.autofocus = false;
                 ui.memory_mut(|memory| {
                     memory.request_focus(resp.id);
                 });
             }
+            if resp.changed() {
+                self.preview = None;
+            }
+            if self.previewing.is_some() {
+                ui.spinner();
+            } else if ui
+                .add_enabled(!self.url.is_empty(), egui::Button::new("👁"))
+                .on_hover_text("Preview the feed")
+                .clicked()
+            {
+                let url = self.url.clone();
+                self.error = None;
+                self.preview = None;
+                self.previewing = Some(store.send(|reply| Command::Preview(url, reply)));
+            }
+            if self.discovering.is_some() {
+                ui.spinner();
+            } else if ui
+                .add_enabled(!self.url.is_empty(), egui::Button::new("🔍"))
+                .on_hover_text("Find feeds of a website")
+                .clicked()
+            {
+                let url = self.url.clone();
+                self.error = None;
+                self.candidates.clear();
+                self.discovering = Some(store.send(|reply| Command::Discover(url, reply)));
+            }
         });
         ui.end_row();
+
+        if self.candidates.len() > 1 {
+            let mut picked = None;
+            ui.horizontal_top(|ui| {
+                ui.add_sized((50., 24.), egui::Label::new("Feeds:"));
+                ui.vertical(|ui| {
+                    for candidate in &self.candidates {
+                        let text = candidate.title.as_deref().unwrap_or(&candidate.url);
+                        if ui
+                            .selectable_label(self.url == candidate.url, text)
+                            .on_hover_text(&candidate.url)
+                            .clicked()
+                        {
+                            picked = Some(candidate.clone());
+                        }
+                    }
+                });
+            });
+            ui.end_row();
+            if let Some(candidate) = picked {
+                self.pick(candidate);
+            }
+        }
         ui.horizontal(|ui| {
             ui.add_sized((50., 24.), egui::Label::new("Name:"));
-            ui.add(egui::TextEdit::singleline(&mut self.name).hint_text("Opional"));
+            ui.add(egui::TextEdit::singleline(&mut self.name).hint_text("Optional"));
         });
         ui.end_row();
+        if let Some(preview) = &self.preview {
+            Self::preview_ui(ui, preview);
+        }
 
         ui.horizontal(|ui| {
             ui.add_sized((50., 24.), egui::Label::new("Folder:"));
@@ -89,7 +244,7 @@ impl View for AddWindow {
                 .show_ui(ui, |ui| {
                     let folder_id = &mut self.folder.id;
                     let folder_name = &mut self.folder.name;
-                    self.folders.as_ref().map(move |folders| {
+                    if let Some(folders) = self.folders.as_ref() {
                         folders.iter().for_each(move |folder| {
                             if ui
                                 .selectable_value(folder_id, folder.id, folder.name.to_string())
@@ -98,30 +253,28 @@ impl View for AddWindow {
                                 *folder_name = folder.name.to_string();
                             }
                         });
-                    });
+                    }
                 });
         });
         ui.end_row();
+        super::error_label(ui, &self.error);
 
         ui.with_layout(
             egui::Layout::default().with_cross_align(emath::Align::RIGHT),
             move |ui| {
                 ui.horizontal_wrapped(move |ui| {
-                    if ui.button("Add").clicked() {
-                        if self.url.is_empty() || self.name.is_empty() {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Add"))
+                        .clicked()
+                    {
+                        // the name falls back to the title of the feed
+                        if self.url.trim().is_empty() {
                             return;
                         }
 
-                        let feed = Feed::new(
-                            mem::replace(&mut self.url, "".to_string()),
-                            mem::replace(&mut self.name, "".to_string()),
-                            self.folder.id,
-                        );
-                        if let Err(e) = store.sender.send(Message::Feed(Action::Create, feed)) {
-                            tracing::error!("{e}");
-                        } else {
-                            self.closed = true;
-                        }
+                        let feed = Feed::new(self.url.clone(), self.name.clone(), self.folder.id);
+                        self.error = None;
+                        self.pending = Some(store.send(|reply| Command::CreateFeed(feed, reply)));
                     }
                 });
             },
@@ -129,10 +282,13 @@ impl View for AddWindow {
     }
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct DeleteWindow {
     feed: Feed,
     closed: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<(), FeedError>>,
 }
 
 impl DeleteWindow {
@@ -154,6 +310,7 @@ impl Window for DeleteWindow {
     ) {
         if let Some(Message::Feed(_, feed)) = data {
             self.feed = feed;
+            self.error = None;
         }
         self.closed = false;
         egui::Window::new(self.name())
@@ -171,25 +328,34 @@ impl Window for DeleteWindow {
 
 impl View for DeleteWindow {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.feed = Feed::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
         ui.label(format!(
             "Are you sure you want to delete the '{}' feed?",
             self.feed.name
         ));
         ui.end_row();
+        super::error_label(ui, &self.error);
 
         ui.with_layout(
             egui::Layout::default().with_cross_align(emath::Align::RIGHT),
             move |ui| {
                 ui.horizontal_wrapped(move |ui| {
-                    if ui.button("Ok").clicked() {
-                        if let Err(e) = store.sender.send(Message::Feed(
-                            Action::Delete,
-                            mem::replace(&mut self.feed, Feed::default()),
-                        )) {
-                            tracing::error!("{e}");
-                        } else {
-                            self.closed = true;
-                        }
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Ok"))
+                        .clicked()
+                    {
+                        let feed = self.feed.clone();
+                        self.error = None;
+                        self.pending = Some(store.send(|reply| Command::DeleteFeed(feed, reply)));
                     }
                 });
             },
@@ -197,13 +363,16 @@ impl View for DeleteWindow {
     }
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct EditWindow {
-    feed: Feed,
+    feed: FeedSettings,
     folder: Folder,
     closed: bool,
     autofocus: bool,
     folders: Option<Vec<Folder>>,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<Feed, FeedError>>,
 }
 
 impl EditWindow {
@@ -226,8 +395,9 @@ impl Window for EditWindow {
         // must
         if let Some(Message::Feed(_, feed)) = data.take() {
             self.autofocus = true;
+            self.error = None;
             let folder_id = feed.folder_id;
-            self.feed = feed;
+            self.feed = FeedSettings::from(&feed);
             if let Ok(reader) = store.folders.read() {
                 self.folder = reader
                     .iter()
@@ -254,6 +424,17 @@ impl Window for EditWindow {
 
 impl View for EditWindow {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.feed = FeedSettings::default();
+                    self.folder = Folder::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.add_sized((50., 24.), egui::Label::new("URL:"));
             let resp =
@@ -279,7 +460,7 @@ impl View for EditWindow {
                 .show_ui(ui, |ui| {
                     let folder_id = &mut self.folder.id;
                     let folder_name = &mut self.folder.name;
-                    self.folders.as_ref().map(move |folders| {
+                    if let Some(folders) = self.folders.as_ref() {
                         folders.iter().for_each(move |folder| {
                             if ui
                                 .selectable_value(folder_id, folder.id, folder.name.to_string())
@@ -288,28 +469,79 @@ impl View for EditWindow {
                                 *folder_name = folder.name.to_string();
                             }
                         });
-                    });
+                    }
                 });
         });
         ui.end_row();
 
+        ui.horizontal(|ui| {
+            ui.add_sized((50., 24.), egui::Label::new("Refresh:"));
+            let mut default = self.feed.interval.is_none();
+            if ui.checkbox(&mut default, "Default").changed() {
+                self.feed.interval = (!default).then_some(60 * 60);
+            }
+            if let Some(interval) = self.feed.interval.as_mut() {
+                // minutes
+                let mut minutes = *interval / 60;
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut minutes)
+                            .clamp_range(1..=7 * 24 * 60)
+                            .suffix(" min"),
+                    )
+                    .changed()
+                {
+                    *interval = minutes * 60;
+                }
+            }
+        });
+        ui.end_row();
+
+        ui.horizontal(|ui| {
+            ui.add_sized((50., 24.), egui::Label::new("Tags:"));
+            ui.checkbox(&mut self.feed.import_categories, "Import categories")
+                .on_hover_text("Categories of articles become tags");
+        });
+        ui.end_row();
+
+        for (label, keep, default, suffix) in [
+            ("Days:", &mut self.feed.keep_days, 30, " days"),
+            ("Keep:", &mut self.feed.keep_articles, 200, " articles"),
+        ] {
+            ui.horizontal(|ui| {
+                ui.add_sized((50., 24.), egui::Label::new(label));
+                let mut global = keep.is_none();
+                if ui
+                    .checkbox(&mut global, "Default")
+                    .on_hover_text("Follows the retention settings")
+                    .changed()
+                {
+                    *keep = (!global).then_some(default);
+                }
+                if let Some(keep) = keep.as_mut() {
+                    super::retention::keep_ui(ui, keep, default, suffix);
+                }
+            });
+            ui.end_row();
+        }
+        super::error_label(ui, &self.error);
+
         ui.with_layout(
             egui::Layout::default().with_cross_align(emath::Align::RIGHT),
             move |ui| {
                 ui.horizontal_wrapped(move |ui| {
-                    if ui.button("Save").clicked() {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Save"))
+                        .clicked()
+                    {
                         if self.feed.url.is_empty() || self.feed.name.is_empty() {
                             return;
                         }
 
-                        let folder = mem::replace(&mut self.folder, Folder::default());
-                        let mut feed = mem::replace(&mut self.feed, Feed::default());
-                        feed.folder_id = folder.id;
-                        if let Err(e) = store.sender.send(Message::Feed(Action::Update, feed)) {
-                            tracing::error!("{e}");
-                        } else {
-                            self.closed = true;
-                        }
+                        let mut feed = self.feed.clone();
+                        feed.folder_id = self.folder.id;
+                        self.error = None;
+                        self.pending = Some(store.send(|reply| Command::UpdateFeed(feed, reply)));
                     }
                 });
             },
@@ -1,20 +1,25 @@
-use std::{
-    mem,
-    ops::{Div, Sub},
-};
+use std::ops::{Div, Sub};
 
 use eframe::{egui, emath};
 use serde::{Deserialize, Serialize};
 
-use crate::{models::Folder, Action, Message, Store};
+use crate::{
+    errors::FolderError,
+    models::Folder,
+    service::{Command, Pending},
+    Message, Store,
+};
 
 use super::{View, Window};
 
-#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct AddWindow {
     name: String,
     closed: bool,
     autofocus: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<Folder, FolderError>>,
 }
 
 impl AddWindow {
@@ -36,6 +41,7 @@ impl Window for AddWindow {
     ) {
         if let Some(Message::Normal) = data {
             self.autofocus = true;
+            self.error = None;
         }
         self.closed = false;
         egui::Window::new(self.name())
@@ -53,6 +59,16 @@ impl Window for AddWindow {
 
 impl View for AddWindow {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.name.clear();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.add_sized((50., 24.), egui::Label::new("Name:"));
             let resp =
@@ -66,22 +82,26 @@ impl View for AddWindow {
         });
 
         ui.end_row();
+        super::error_label(ui, &self.error);
 
         ui.with_layout(
             egui::Layout::default().with_cross_align(emath::Align::RIGHT),
             move |ui| {
                 ui.horizontal_wrapped(move |ui| {
-                    if ui.button("Add").clicked() {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Add"))
+                        .clicked()
+                    {
                         if self.name.is_empty() {
                             return;
                         }
-                        let mut folder = Folder::default();
-                        folder.name = mem::replace(&mut self.name, "".to_string());
-                        if let Err(e) = store.sender.send(Message::Folder(Action::Create, folder)) {
-                            tracing::error!("{e}");
-                        } else {
-                            self.closed = true;
-                        }
+                        let folder = Folder {
+                            name: self.name.clone(),
+                            ..Default::default()
+                        };
+                        self.error = None;
+                        self.pending =
+                            Some(store.send(|reply| Command::CreateFolder(folder, reply)));
                     }
                 });
             },
@@ -89,10 +109,13 @@ impl View for AddWindow {
     }
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct DeleteWindow {
     folder: Folder,
     closed: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<(), FolderError>>,
 }
 
 impl DeleteWindow {
@@ -114,6 +137,7 @@ impl Window for DeleteWindow {
     ) {
         if let Some(Message::Folder(_, folder)) = data {
             self.folder = folder;
+            self.error = None;
         }
         self.closed = false;
         egui::Window::new(self.name())
@@ -131,25 +155,35 @@ impl Window for DeleteWindow {
 
 impl View for DeleteWindow {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.folder = Folder::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
         ui.label(format!(
             "Are you sure you want to delete the '{}' folder?",
             self.folder.name
         ));
         ui.end_row();
+        super::error_label(ui, &self.error);
 
         ui.with_layout(
             egui::Layout::default().with_cross_align(emath::Align::RIGHT),
             move |ui| {
                 ui.horizontal_wrapped(move |ui| {
-                    if ui.button("Ok").clicked() {
-                        if let Err(e) = store.sender.send(Message::Folder(
-                            Action::Delete,
-                            mem::replace(&mut self.folder, Folder::default()),
-                        )) {
-                            tracing::error!("{e}");
-                        } else {
-                            self.closed = true;
-                        }
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Ok"))
+                        .clicked()
+                    {
+                        let folder = self.folder.clone();
+                        self.error = None;
+                        self.pending =
+                            Some(store.send(|reply| Command::DeleteFolder(folder, reply)));
                     }
                 });
             },
@@ -157,12 +191,15 @@ impl View for DeleteWindow {
     }
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct EditWindow {
     name: String,
     folder: Folder,
     closed: bool,
     autofocus: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<Folder, FolderError>>,
 }
 
 impl EditWindow {
@@ -184,6 +221,7 @@ impl Window for EditWindow {
     ) {
         if let Some(Message::Folder(_, folder)) = data.take() {
             self.autofocus = true;
+            self.error = None;
             self.name = folder.name.clone();
             self.folder = folder;
         }
@@ -204,6 +242,17 @@ impl Window for EditWindow {
 
 impl View for EditWindow {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.name.clear();
+                    self.folder = Folder::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.add_sized((50., 24.), egui::Label::new("Name:"));
             let resp =
@@ -214,22 +263,24 @@ impl View for EditWindow {
             }
         });
         ui.end_row();
+        super::error_label(ui, &self.error);
 
         ui.with_layout(
             egui::Layout::default().with_cross_align(emath::Align::RIGHT),
             move |ui| {
                 ui.horizontal_wrapped(move |ui| {
-                    if ui.button("Rename").clicked() {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Rename"))
+                        .clicked()
+                    {
                         if self.name.is_empty() || self.name == self.folder.name {
                             return;
                         }
-                        let mut folder = mem::replace(&mut self.folder, Folder::default());
-                        folder.name = mem::replace(&mut self.name, "".to_string());
-                        if let Err(e) = store.sender.send(Message::Folder(Action::Update, folder)) {
-                            tracing::error!("{e}");
-                        } else {
-                            self.closed = true;
-                        }
+                        let mut folder = self.folder.clone();
+                        folder.name = self.name.clone();
+                        self.error = None;
+                        self.pending =
+                            Some(store.send(|reply| Command::RenameFolder(folder, reply)));
                     }
                 });
             },
@@ -1,9 +1,15 @@
+use std::time::Duration;
+
 use eframe::egui;
 
-use crate::{Message, Store};
+use crate::{errors::Gone, service::Pending, Message, Store};
 
 pub mod feed;
 pub mod folder;
+pub mod opml;
+pub mod retention;
+pub mod rules;
+pub mod smart;
 
 pub trait View {
     fn ui(&mut self, ui: &mut egui::Ui, store: &Store);
@@ -27,3 +33,24 @@ pub trait Window {
     /// status
     fn is_closed(&self) -> bool;
 }
+
+/// Polls the outcome of a command, `Some` once the worker answered.
+fn poll<T, E: From<Gone>>(
+    ui: &egui::Ui,
+    pending: &mut Option<Pending<T, E>>,
+) -> Option<Result<T, E>> {
+    let result = pending.as_mut()?.try_recv();
+    if result.is_some() {
+        *pending = None;
+    } else {
+        ui.ctx().request_repaint_after(Duration::from_millis(100));
+    }
+    result
+}
+
+fn error_label(ui: &mut egui::Ui, error: &Option<String>) {
+    if let Some(error) = error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+        ui.end_row();
+    }
+}
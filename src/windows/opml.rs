@@ -0,0 +1,232 @@
+use std::{
+    env,
+    ops::{Div, Sub},
+    path::PathBuf,
+};
+
+use eframe::{egui, emath};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{ExportError, ImportError},
+    opml::ImportReport,
+    service::{Command, Pending},
+    Message, Store,
+};
+
+use super::{View, Window};
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct ImportWindow {
+    path: String,
+    closed: bool,
+    autofocus: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    report: Option<ImportReport>,
+    #[serde(skip)]
+    pending: Option<Pending<ImportReport, ImportError>>,
+}
+
+impl ImportWindow {
+    pub const NAME: &'static str = "Import OPML";
+
+    fn import(&mut self, store: &Store) {
+        let path = PathBuf::from(self.path.trim());
+        self.error = None;
+        self.report = None;
+        self.pending = Some(store.send(|reply| Command::ImportOpml(path, reply)));
+    }
+}
+
+impl Window for ImportWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        mut data: Option<Message>,
+    ) {
+        match data.take() {
+            Some(Message::Normal) => {
+                self.autofocus = true;
+                self.error = None;
+                self.report = None;
+            }
+            // dropped onto the window
+            Some(Message::File(path)) => {
+                self.path = path.display().to_string();
+                self.import(store);
+            }
+            _ => {}
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .default_width(280.0)
+            .default_pos(size.sub(egui::vec2(280.0, 600.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for ImportWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(report) => self.report = Some(report),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_sized((50., 24.), egui::Label::new("File:"));
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.path).hint_text("Write or drop an .opml file"),
+            );
+            if self.autofocus {
+                self.autofocus = false;
+                ui.memory_mut(|memory| memory.request_focus(resp.id));
+            }
+        });
+        ui.end_row();
+        if let Some(report) = &self.report {
+            ui.label(report.to_string());
+            ui.end_row();
+        }
+        super::error_label(ui, &self.error);
+
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            move |ui| {
+                ui.horizontal_wrapped(move |ui| {
+                    if self.pending.is_some() {
+                        ui.spinner();
+                    }
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Import"))
+                        .clicked()
+                    {
+                        if self.path.trim().is_empty() {
+                            return;
+                        }
+                        self.import(store);
+                    }
+                });
+            },
+        );
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct ExportWindow {
+    path: String,
+    closed: bool,
+    autofocus: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    exported: Option<usize>,
+    #[serde(skip)]
+    pending: Option<Pending<usize, ExportError>>,
+}
+
+impl ExportWindow {
+    pub const NAME: &'static str = "Export OPML";
+}
+
+impl Window for ExportWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        data: Option<Message>,
+    ) {
+        if let Some(Message::Normal) = data {
+            self.autofocus = true;
+            self.error = None;
+            self.exported = None;
+            if self.path.is_empty() {
+                if let Ok(home) = env::var("HOME") {
+                    self.path = format!("{home}/pindash-news.opml");
+                }
+            }
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .default_width(280.0)
+            .default_pos(size.sub(egui::vec2(280.0, 600.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for ExportWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(feeds) => self.exported = Some(feeds),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_sized((50., 24.), egui::Label::new("File:"));
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.path).hint_text("Write the path to save to"),
+            );
+            if self.autofocus {
+                self.autofocus = false;
+                ui.memory_mut(|memory| memory.request_focus(resp.id));
+            }
+            if resp.changed() {
+                self.exported = None;
+            }
+        });
+        ui.end_row();
+        if let Some(feeds) = self.exported {
+            ui.label(format!("{feeds} feeds exported"));
+            ui.end_row();
+        }
+        super::error_label(ui, &self.error);
+
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            move |ui| {
+                ui.horizontal_wrapped(move |ui| {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Export"))
+                        .clicked()
+                    {
+                        if self.path.trim().is_empty() {
+                            return;
+                        }
+                        let path = PathBuf::from(self.path.trim());
+                        self.error = None;
+                        self.exported = None;
+                        self.pending = Some(store.send(|reply| Command::ExportOpml(path, reply)));
+                    }
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::ops::{Div, Sub};
+
+use eframe::{egui, emath};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::FeedError,
+    models::{Pruned, Retention},
+    service::{Command, Pending},
+    utils, Message, Store,
+};
+
+use super::{View, Window};
+
+/// Articles kept by default, and pruning on demand.
+#[derive(Default, Deserialize, Serialize)]
+pub struct RetentionWindow {
+    retention: Retention,
+    closed: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pruned: Option<Pruned>,
+    #[serde(skip)]
+    loading: Option<Pending<Retention, FeedError>>,
+    #[serde(skip)]
+    saving: Option<Pending<Retention, FeedError>>,
+    #[serde(skip)]
+    pruning: Option<Pending<Pruned, FeedError>>,
+}
+
+impl RetentionWindow {
+    pub const NAME: &'static str = "Retention";
+}
+
+impl Window for RetentionWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        data: Option<Message>,
+    ) {
+        if let Some(Message::Normal) = data {
+            self.error = None;
+            self.pruned = None;
+            self.loading = Some(store.send(Command::Retention));
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .default_width(320.0)
+            .default_pos(size.sub(egui::vec2(320.0, 400.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for RetentionWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.loading) {
+            match result {
+                Ok(retention) => self.retention = retention,
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.saving) {
+            match result {
+                Ok(_) => self.closed = true,
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.pruning) {
+            match result {
+                Ok(pruned) => self.pruned = Some(pruned),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        egui::Grid::new("retention")
+            .num_columns(2)
+            .spacing([8.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Keep days:");
+                keep_ui(ui, &mut self.retention.keep_days, 30, " days");
+                ui.end_row();
+
+                ui.label("Keep articles:");
+                keep_ui(ui, &mut self.retention.keep_articles, 200, " per feed");
+                ui.end_row();
+            });
+        ui.weak("Starred and unread articles are always kept.");
+
+        if let Some(Pruned { articles, freed }) = self.pruned {
+            ui.label(format!(
+                "Pruned {articles} articles, freed {}",
+                utils::size(freed)
+            ));
+        }
+        super::error_label(ui, &self.error);
+
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    if ui
+                        .add_enabled(self.saving.is_none(), egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        let retention = self.retention;
+                        self.error = None;
+                        self.saving =
+                            Some(store.send(|reply| Command::SetRetention(retention, reply)));
+                    }
+                    if ui
+                        .add_enabled(self.pruning.is_none(), egui::Button::new("Prune now"))
+                        .on_hover_text("Uses the saved settings")
+                        .clicked()
+                    {
+                        self.error = None;
+                        self.pruned = None;
+                        self.pruning = Some(store.send(Command::Prune));
+                    }
+                });
+            },
+        );
+    }
+}
+
+/// `0` keeps everything.
+pub fn keep_ui(ui: &mut egui::Ui, keep: &mut u32, default: u32, suffix: &str) {
+    ui.horizontal(|ui| {
+        let mut all = *keep == 0;
+        if ui.checkbox(&mut all, "All").changed() {
+            *keep = if all { 0 } else { default };
+        }
+        if *keep > 0 {
+            ui.add(
+                egui::DragValue::new(keep)
+                    .clamp_range(1..=100_000)
+                    .suffix(suffix),
+            );
+        }
+    });
+}
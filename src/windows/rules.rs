@@ -0,0 +1,355 @@
+use std::ops::{Div, Sub};
+
+use eframe::{egui, emath};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::RuleError,
+    rules::{Action, Condition, DryRun, Rule},
+    service::{Command, Pending},
+    Message, Store,
+};
+
+use super::{View, Window};
+
+/// Lists the rules and edits one of them.
+#[derive(Default, Deserialize, Serialize)]
+pub struct RulesWindow {
+    rules: Vec<Rule>,
+    /// being edited, a new one when its id is `0`
+    rule: Rule,
+    closed: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    dry_run: Option<DryRun>,
+    #[serde(skip)]
+    loading: Option<Pending<Vec<Rule>, RuleError>>,
+    #[serde(skip)]
+    saving: Option<Pending<Rule, RuleError>>,
+    #[serde(skip)]
+    deleting: Option<Pending<(), RuleError>>,
+    #[serde(skip)]
+    testing: Option<Pending<DryRun, RuleError>>,
+}
+
+impl RulesWindow {
+    pub const NAME: &'static str = "Rules";
+
+    fn reload(&mut self, store: &Store) {
+        self.loading = Some(store.send(Command::Rules));
+    }
+
+    fn edit(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.dry_run = None;
+        self.error = None;
+    }
+
+    fn list_ui(&mut self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            ui.set_width(160.0);
+            if ui.button("New rule").clicked() {
+                self.edit(Rule::default());
+            }
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .id_source("rules")
+                .max_height(360.0)
+                .show(ui, |ui| {
+                    let mut selected = None;
+                    for rule in &self.rules {
+                        let text = if rule.enabled {
+                            egui::RichText::new(&rule.name)
+                        } else {
+                            egui::RichText::new(&rule.name).weak()
+                        };
+                        if ui.selectable_label(rule.id == self.rule.id, text).clicked() {
+                            selected = Some(rule.clone());
+                        }
+                    }
+                    if let Some(rule) = selected {
+                        self.edit(rule);
+                    }
+                });
+        });
+    }
+
+    fn editor_ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        ui.vertical(|ui| {
+            egui::Grid::new("rule")
+                .num_columns(2)
+                .spacing([8.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Name:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.rule.name)
+                            .hint_text("Write rule name"),
+                    );
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut self.rule.enabled, "Enabled");
+                    ui.end_row();
+                });
+
+            ui.separator();
+            ui.label("When every condition matches a new article:");
+            conditions_ui(ui, store, &mut self.rule.conditions);
+            if ui.button("➕ Condition").clicked() {
+                self.rule.conditions.push(Condition::of_kind("Title"));
+            }
+
+            ui.separator();
+            ui.label("Then:");
+            actions_ui(ui, store, &mut self.rule.actions);
+            if ui.button("➕ Action").clicked() {
+                self.rule.actions.push(Action::MarkRead);
+            }
+
+            ui.separator();
+            super::error_label(ui, &self.error);
+            self.buttons_ui(ui, store);
+            self.dry_run_ui(ui);
+        });
+    }
+
+    fn buttons_ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        let idle = self.saving.is_none() && self.deleting.is_none();
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    if ui.add_enabled(idle, egui::Button::new("Save")).clicked() {
+                        let mut rule = self.rule.clone();
+                        rule.name = rule.name.trim().to_owned();
+                        if rule.name.is_empty() {
+                            self.error = Some("name is required".to_owned());
+                        } else {
+                            self.error = None;
+                            self.saving = Some(store.send(|reply| Command::SaveRule(rule, reply)));
+                        }
+                    }
+                    if ui
+                        .add_enabled(self.testing.is_none(), egui::Button::new("Dry run"))
+                        .on_hover_text("Existing articles the rule would have matched")
+                        .clicked()
+                    {
+                        let rule = self.rule.clone();
+                        self.dry_run = None;
+                        self.testing = Some(store.send(|reply| Command::DryRun(rule, reply)));
+                    }
+                    if self.rule.id != 0
+                        && ui.add_enabled(idle, egui::Button::new("Delete")).clicked()
+                    {
+                        let rule = self.rule.clone();
+                        self.deleting = Some(store.send(|reply| Command::DeleteRule(rule, reply)));
+                    }
+                });
+            },
+        );
+    }
+
+    fn dry_run_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(dry_run) = &self.dry_run else {
+            return;
+        };
+        ui.separator();
+        ui.label(match dry_run.matched {
+            0 => "No existing article matches".to_owned(),
+            1 => "1 existing article matches".to_owned(),
+            n => format!("{n} existing articles match"),
+        });
+        if dry_run.truncated {
+            ui.weak(format!("of the latest {} articles", dry_run.scanned));
+        }
+        egui::ScrollArea::vertical()
+            .id_source("dry run")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for article in &dry_run.articles {
+                    ui.label(&article.title).on_hover_text(&article.url);
+                }
+                if dry_run.matched > dry_run.articles.len() {
+                    ui.weak(format!(
+                        "and {} more",
+                        dry_run.matched - dry_run.articles.len()
+                    ));
+                }
+            });
+    }
+}
+
+fn conditions_ui(ui: &mut egui::Ui, store: &Store, conditions: &mut Vec<Condition>) {
+    let mut removed = None;
+    for (i, condition) in conditions.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let kind = condition.kind();
+            egui::ComboBox::from_id_source(("rule condition", i))
+                .width(90.0)
+                .selected_text(kind)
+                .show_ui(ui, |ui| {
+                    for k in Condition::KINDS {
+                        if ui.selectable_label(k == kind, k).clicked() && k != kind {
+                            *condition = Condition::of_kind(k);
+                        }
+                    }
+                });
+            match condition {
+                Condition::Title(text)
+                | Condition::Content(text)
+                | Condition::Author(text)
+                | Condition::Url(text)
+                | Condition::Category(text) => {
+                    ui.add(egui::TextEdit::singleline(text).hint_text("contains"));
+                }
+                Condition::Feed(id) => feed_ui(ui, store, ("rule feed", i), id),
+            }
+            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        conditions.remove(i);
+    }
+}
+
+fn actions_ui(ui: &mut egui::Ui, store: &Store, actions: &mut Vec<Action>) {
+    let mut removed = None;
+    for (i, action) in actions.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let kind = action.kind();
+            egui::ComboBox::from_id_source(("rule action", i))
+                .width(120.0)
+                .selected_text(kind)
+                .show_ui(ui, |ui| {
+                    for k in Action::KINDS {
+                        if ui.selectable_label(k == kind, k).clicked() && k != kind {
+                            *action = Action::of_kind(k);
+                        }
+                    }
+                });
+            match action {
+                Action::Tag(tag) => {
+                    ui.add(egui::TextEdit::singleline(tag).hint_text("Tag name"));
+                }
+                Action::Folder(id) => folder_ui(ui, store, ("rule folder", i), id),
+                Action::MarkRead | Action::Star | Action::Drop => {}
+            }
+            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        actions.remove(i);
+    }
+}
+
+fn feed_ui(ui: &mut egui::Ui, store: &Store, id_source: impl std::hash::Hash, id: &mut u64) {
+    let Ok(folders) = store.folders.try_read() else {
+        return;
+    };
+    let feeds = || folders.iter().filter_map(|f| f.feeds.as_ref()).flatten();
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(
+            feeds()
+                .find(|feed| feed.id == *id)
+                .map_or("Choose a feed", |feed| feed.name.as_str()),
+        )
+        .show_ui(ui, |ui| {
+            for feed in feeds() {
+                ui.selectable_value(id, feed.id, &feed.name);
+            }
+        });
+}
+
+fn folder_ui(ui: &mut egui::Ui, store: &Store, id_source: impl std::hash::Hash, id: &mut u64) {
+    let Ok(folders) = store.folders.try_read() else {
+        return;
+    };
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(
+            folders
+                .iter()
+                .find(|folder| folder.id == *id)
+                .map_or("Choose a folder", |folder| folder.name.as_str()),
+        )
+        .show_ui(ui, |ui| {
+            for folder in folders.iter() {
+                ui.selectable_value(id, folder.id, &folder.name);
+            }
+        });
+}
+
+impl Window for RulesWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        data: Option<Message>,
+    ) {
+        if let Some(Message::Normal) = data {
+            self.edit(Rule::default());
+            self.reload(store);
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .default_width(560.0)
+            .default_pos(size.sub(egui::vec2(560.0, 600.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for RulesWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.loading) {
+            match result {
+                Ok(rules) => self.rules = rules,
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.saving) {
+            match result {
+                Ok(rule) => {
+                    self.rule = rule;
+                    self.reload(store);
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.deleting) {
+            match result {
+                Ok(()) => {
+                    self.edit(Rule::default());
+                    self.reload(store);
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        if let Some(result) = super::poll(ui, &mut self.testing) {
+            match result {
+                Ok(dry_run) => self.dry_run = Some(dry_run),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        ui.horizontal_top(|ui| {
+            self.list_ui(ui);
+            ui.separator();
+            self.editor_ui(ui, store);
+        });
+    }
+}
@@ -0,0 +1,476 @@
+use std::ops::{Div, Sub};
+
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use eframe::{egui, emath};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::FolderError,
+    models::{SmartFilter, SmartFolder},
+    service::{Command, Pending},
+    Message, Store,
+};
+
+use super::{View, Window};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Fields of a smart folder, dates are local `YYYY-MM-DD`.
+#[derive(Default, Deserialize, Serialize)]
+struct Form {
+    name: String,
+    keywords: String,
+    feeds: Vec<u64>,
+    folders: Vec<u64>,
+    /// comma separated
+    authors: String,
+    read: Option<bool>,
+    starred: Option<bool>,
+    since: String,
+    /// included
+    until: String,
+}
+
+impl Form {
+    fn new(folder: &SmartFolder) -> Self {
+        let filter = &folder.filter;
+        Self {
+            name: folder.name.clone(),
+            keywords: filter.keywords.clone(),
+            feeds: filter.feeds.clone(),
+            folders: filter.folders.clone(),
+            authors: filter.authors.join(", "),
+            read: filter.read,
+            starred: filter.starred,
+            since: filter.since.map(format_date).unwrap_or_default(),
+            // the filter ends at midnight of the next day
+            until: filter
+                .until
+                .map(|until| format_date(until - 1))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn smart_folder(&self, id: u64) -> Result<SmartFolder, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("name is required".to_owned());
+        }
+        let since = parse_date(&self.since).map_err(|e| format!("since: {e}"))?;
+        let until = parse_date(&self.until).map_err(|e| format!("until: {e}"))?;
+        Ok(SmartFolder {
+            id,
+            name: name.to_owned(),
+            filter: SmartFilter {
+                keywords: self.keywords.trim().to_owned(),
+                feeds: self.feeds.clone(),
+                folders: self.folders.clone(),
+                authors: self
+                    .authors
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+                read: self.read,
+                starred: self.starred,
+                since: since.and_then(midnight),
+                until: until.and_then(|d| midnight(d + Duration::days(1))),
+            },
+            unread: 0,
+        })
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store, autofocus: &mut bool) {
+        egui::Grid::new("smart folder")
+            .num_columns(2)
+            .spacing([8.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Name:");
+                let resp = ui
+                    .add(egui::TextEdit::singleline(&mut self.name).hint_text("Write folder name"));
+                if *autofocus {
+                    *autofocus = false;
+                    ui.memory_mut(|memory| memory.request_focus(resp.id));
+                }
+                ui.end_row();
+
+                ui.label("Keywords:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.keywords)
+                        .hint_text("Words, \"phrases\" or prefix*"),
+                );
+                ui.end_row();
+
+                ui.label("Feeds:");
+                self.feeds_ui(ui, store);
+                ui.end_row();
+
+                ui.label("Authors:");
+                ui.add(egui::TextEdit::singleline(&mut self.authors).hint_text("Comma separated"));
+                ui.end_row();
+
+                ui.label("State:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("smart folder read")
+                        .selected_text(match self.read {
+                            None => "Read or unread",
+                            Some(false) => "Unread",
+                            Some(true) => "Read",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.read, None, "Read or unread");
+                            ui.selectable_value(&mut self.read, Some(false), "Unread");
+                            ui.selectable_value(&mut self.read, Some(true), "Read");
+                        });
+                    egui::ComboBox::from_id_source("smart folder starred")
+                        .selected_text(match self.starred {
+                            None => "Starred or not",
+                            Some(true) => "Starred",
+                            Some(false) => "Not starred",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.starred, None, "Starred or not");
+                            ui.selectable_value(&mut self.starred, Some(true), "Starred");
+                            ui.selectable_value(&mut self.starred, Some(false), "Not starred");
+                        });
+                });
+                ui.end_row();
+
+                ui.label("Dates:");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.since)
+                            .hint_text("Since YYYY-MM-DD")
+                            .desired_width(110.0),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.until)
+                            .hint_text("Until YYYY-MM-DD")
+                            .desired_width(110.0),
+                    );
+                });
+                ui.end_row();
+            });
+    }
+
+    /// Folders with their feeds, no selection matches every feed.
+    fn feeds_ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        let selected = self.feeds.len() + self.folders.len();
+        egui::CollapsingHeader::new(if selected == 0 {
+            "All feeds".to_owned()
+        } else {
+            format!("{selected} selected")
+        })
+        .id_source("smart folder feeds")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(180.0)
+                .show(ui, |ui| {
+                    let Ok(folders) = store.folders.try_read() else {
+                        return;
+                    };
+                    for folder in folders.iter() {
+                        toggle(ui, &mut self.folders, folder.id, &folder.name);
+                        ui.indent(folder.id, |ui| {
+                            for feed in folder.feeds.iter().flatten() {
+                                toggle(ui, &mut self.feeds, feed.id, &feed.name);
+                            }
+                        });
+                    }
+                });
+        });
+    }
+}
+
+fn toggle(ui: &mut egui::Ui, ids: &mut Vec<u64>, id: u64, label: &str) {
+    let mut checked = ids.contains(&id);
+    if ui.checkbox(&mut checked, label).changed() {
+        if checked {
+            ids.push(id);
+        } else {
+            ids.retain(|i| *i != id);
+        }
+    }
+}
+
+fn parse_date(date: &str) -> Result<Option<NaiveDate>, chrono::ParseError> {
+    let date = date.trim();
+    if date.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(date, DATE_FORMAT).map(Some)
+}
+
+/// Local midnight of a date in milliseconds.
+fn midnight(date: NaiveDate) -> Option<i64> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Some(
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()?
+            .timestamp_millis(),
+    )
+}
+
+fn format_date(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.format(DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct AddWindow {
+    form: Form,
+    closed: bool,
+    autofocus: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<SmartFolder, FolderError>>,
+}
+
+impl AddWindow {
+    pub const NAME: &'static str = "Add Smart Folder";
+}
+
+impl Window for AddWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        data: Option<Message>,
+    ) {
+        if let Some(Message::Normal) = data {
+            self.autofocus = true;
+            self.error = None;
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .default_width(360.0)
+            .default_pos(size.sub(egui::vec2(360.0, 600.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for AddWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.form = Form::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        self.form.ui(ui, store, &mut self.autofocus);
+        super::error_label(ui, &self.error);
+
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            move |ui| {
+                ui.horizontal_wrapped(move |ui| {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Add"))
+                        .clicked()
+                    {
+                        match self.form.smart_folder(0) {
+                            Ok(folder) => {
+                                self.error = None;
+                                self.pending = Some(
+                                    store.send(|reply| Command::CreateSmartFolder(folder, reply)),
+                                );
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                });
+            },
+        );
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct EditWindow {
+    id: u64,
+    form: Form,
+    closed: bool,
+    autofocus: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<SmartFolder, FolderError>>,
+}
+
+impl EditWindow {
+    pub const NAME: &'static str = "Edit Smart Folder";
+}
+
+impl Window for EditWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        mut data: Option<Message>,
+    ) {
+        if let Some(Message::SmartFolder(_, folder)) = data.take() {
+            self.autofocus = true;
+            self.error = None;
+            self.id = folder.id;
+            self.form = Form::new(&folder);
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .collapsible(false)
+            .default_width(360.0)
+            .default_pos(size.sub(egui::vec2(360.0, 600.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for EditWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.form = Form::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        self.form.ui(ui, store, &mut self.autofocus);
+        super::error_label(ui, &self.error);
+
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            move |ui| {
+                ui.horizontal_wrapped(move |ui| {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        match self.form.smart_folder(self.id) {
+                            Ok(folder) => {
+                                self.error = None;
+                                self.pending = Some(
+                                    store.send(|reply| Command::UpdateSmartFolder(folder, reply)),
+                                );
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                });
+            },
+        );
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct DeleteWindow {
+    folder: SmartFolder,
+    closed: bool,
+    error: Option<String>,
+    #[serde(skip)]
+    pending: Option<Pending<(), FolderError>>,
+}
+
+impl DeleteWindow {
+    pub const NAME: &'static str = "Delete Smart Folder";
+}
+
+impl Window for DeleteWindow {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn show(
+        &mut self,
+        store: &Store,
+        ctx: &egui::Context,
+        open: &mut bool,
+        size: egui::Vec2,
+        data: Option<Message>,
+    ) {
+        if let Some(Message::SmartFolder(_, folder)) = data {
+            self.folder = folder;
+            self.error = None;
+        }
+        self.closed = false;
+        egui::Window::new(self.name())
+            .resizable(false)
+            .default_width(280.0)
+            .default_pos(size.sub(egui::vec2(280.0, 600.0)).div(2.0).to_pos2())
+            .open(open)
+            .show(ctx, |ui| self.ui(ui, store));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl View for DeleteWindow {
+    fn ui(&mut self, ui: &mut egui::Ui, store: &Store) {
+        if let Some(result) = super::poll(ui, &mut self.pending) {
+            match result {
+                Ok(_) => {
+                    self.folder = SmartFolder::default();
+                    self.closed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        ui.label(format!(
+            "Are you sure you want to delete the '{}' smart folder? Its articles are kept.",
+            self.folder.name
+        ));
+        ui.end_row();
+        super::error_label(ui, &self.error);
+
+        ui.with_layout(
+            egui::Layout::default().with_cross_align(emath::Align::RIGHT),
+            move |ui| {
+                ui.horizontal_wrapped(move |ui| {
+                    if ui
+                        .add_enabled(self.pending.is_none(), egui::Button::new("Ok"))
+                        .clicked()
+                    {
+                        let folder = self.folder.clone();
+                        self.error = None;
+                        self.pending =
+                            Some(store.send(|reply| Command::DeleteSmartFolder(folder, reply)));
+                    }
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+
+use anyhow::Result;
+use pindash_news::discovery::{self, Candidate};
+use url::Url;
+
+fn candidate(url: &str, title: Option<&str>, kind: Option<&str>) -> Candidate {
+    Candidate {
+        url: url.to_owned(),
+        title: title.map(ToOwned::to_owned),
+        kind: kind.map(ToOwned::to_owned),
+    }
+}
+
+#[test]
+fn parse_alternate_links() -> Result<()> {
+    let html = fs::read_to_string("tests/fixtures/discovery.html")?;
+    let base = Url::parse("https://example.com/blog/")?;
+
+    let candidates = discovery::parse_candidates(&base, &html);
+
+    assert_eq!(
+        candidates,
+        [
+            candidate(
+                "https://example.com/feed.xml",
+                Some("Example Blog"),
+                Some("application/rss+xml")
+            ),
+            candidate(
+                "https://example.com/blog/comments/atom.xml",
+                Some("Comments"),
+                Some("application/atom+xml")
+            ),
+            candidate(
+                "https://example.com/feed.json",
+                None,
+                Some("application/feed+json")
+            ),
+            candidate(
+                "https://example.com/podcast/rss",
+                Some("Podcast"),
+                Some("application/rss+xml")
+            ),
+            candidate(
+                "https://example.com/tags/rust/index.xml",
+                Some("Rust"),
+                None
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_base_href() -> Result<()> {
+    let html = r#"<html><head>
+        <base href="https://cdn.example.com/site/">
+        <link rel="alternate" type="application/atom+xml" href="atom.xml">
+    </head></html>"#;
+    let base = Url::parse("https://example.com/")?;
+
+    let candidates = discovery::parse_candidates(&base, html);
+
+    assert_eq!(
+        candidates,
+        [candidate(
+            "https://cdn.example.com/site/atom.xml",
+            None,
+            Some("application/atom+xml")
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_without_feeds() -> Result<()> {
+    let html = r#"<html><head><title>Nothing</title></head>
+        <body><a href="/about">About</a></body></html>"#;
+    let base = Url::parse("https://example.com/")?;
+
+    assert!(discovery::parse_candidates(&base, html).is_empty());
+    Ok(())
+}
+
+#[test]
+fn common_paths_of_site() -> Result<()> {
+    let base = Url::parse("https://example.com/blog/post.html?id=1")?;
+
+    let urls = discovery::common_paths(&base)
+        .into_iter()
+        .map(|c| c.url)
+        .collect::<Vec<_>>();
+
+    assert!(urls.contains(&"https://example.com/feed".to_owned()));
+    assert!(urls.contains(&"https://example.com/rss.xml".to_owned()));
+    assert!(urls.contains(&"https://example.com/atom.xml".to_owned()));
+    assert!(urls.contains(&"https://example.com/index.xml".to_owned()));
+    Ok(())
+}
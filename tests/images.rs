@@ -0,0 +1,50 @@
+use std::{env, fs, io::Cursor, process, thread, time::Duration};
+
+use anyhow::Result;
+use image::{ImageOutputFormat, RgbaImage};
+use pindash_news::images::{self, ImageCache};
+
+fn png(width: u32, height: u32) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    RgbaImage::new(width, height).write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+    Ok(bytes)
+}
+
+#[test]
+fn cached_images_are_evicted_least_recently_used_first() -> Result<()> {
+    let dir = env::temp_dir().join(format!("pindash-news-{}-images", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let cache = ImageCache::with_capacity(dir.clone(), 25);
+    assert!(cache.get("https://example.com/a.png").is_none());
+
+    cache.insert("https://example.com/a.png", &[1; 10])?;
+    // named by a hash which is stable across builds
+    assert!(dir.join("fa378f653a3984b6").is_file());
+    // modification times are apart
+    thread::sleep(Duration::from_millis(20));
+    cache.insert("https://example.com/b.png", &[2; 10])?;
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(cache.get("https://example.com/a.png"), Some(vec![1; 10]));
+    thread::sleep(Duration::from_millis(20));
+
+    cache.insert("https://example.com/c.png", &[3; 10])?;
+    assert_eq!(cache.size()?, 20);
+    assert!(cache.get("https://example.com/b.png").is_none());
+    assert!(cache.get("https://example.com/a.png").is_some());
+    assert!(cache.get("https://example.com/c.png").is_some());
+
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn decode_scales_large_images_down() -> Result<()> {
+    let image = images::decode(&png(40, 30)?)?;
+    assert_eq!(image.dimensions(), (40, 30));
+
+    let image = images::decode(&png(4096, 64)?)?;
+    assert_eq!(image.dimensions(), (2048, 32));
+
+    assert!(images::decode(b"<html>not found</html>").is_err());
+    Ok(())
+}
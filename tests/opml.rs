@@ -0,0 +1,184 @@
+use std::fs;
+
+use anyhow::Result;
+use pindash_news::{
+    errors::ImportError,
+    models::{Feed, Folder},
+    opml,
+};
+
+/// Folder names with the urls of their feeds.
+fn folders(opml: &opml::Opml) -> Vec<(&str, Vec<&str>)> {
+    opml.folders
+        .iter()
+        .map(|f| {
+            (
+                f.name.as_str(),
+                f.feeds.iter().flatten().map(|f| f.url.as_str()).collect(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn parse_groups_and_feeds() -> Result<()> {
+    let xml = fs::read_to_string("tests/fixtures/subscriptions.opml")?;
+
+    let opml = opml::parse(&xml)?;
+
+    assert_eq!(
+        folders(&opml),
+        [
+            (
+                "Rust",
+                vec![
+                    "https://blog.rust-lang.org/feed.xml",
+                    "https://this-week-in-rust.org/atom.xml",
+                ]
+            ),
+            (
+                "Nightly",
+                vec!["https://blog.rust-lang.org/inside-rust/feed.xml"]
+            ),
+            ("News & Views", vec!["https://news.ycombinator.com/rss"]),
+            ("", vec!["https://example.com/feed.xml"]),
+        ]
+    );
+    assert_eq!(opml.invalid, ["not a url", "file:///etc/passwd"]);
+    Ok(())
+}
+
+#[test]
+fn parse_feed_names() -> Result<()> {
+    let xml = fs::read_to_string("tests/fixtures/subscriptions.opml")?;
+
+    let opml = opml::parse(&xml)?;
+    let names = opml
+        .folders
+        .iter()
+        .filter_map(|f| f.feeds.as_ref())
+        .flatten()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+
+    // `title` first, then `text`, then the url
+    assert_eq!(
+        names,
+        [
+            "Rust Blog",
+            "This Week in Rust",
+            "Inside Rust",
+            "Hacker News",
+            "https://example.com/feed.xml",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_opml_1() -> Result<()> {
+    let xml = r#"<opml version="1.0"><body>
+        <outline text="Example" xmlUrl="https://example.com/rss"/>
+    </body></opml>"#;
+
+    let opml = opml::parse(xml)?;
+
+    assert_eq!(folders(&opml), [("", vec!["https://example.com/rss"])]);
+    assert!(opml.invalid.is_empty());
+    Ok(())
+}
+
+#[test]
+fn parse_not_opml() {
+    assert!(matches!(
+        opml::parse(r#"<rss version="2.0"><channel/></rss>"#),
+        Err(ImportError::NotOpml)
+    ));
+    assert!(matches!(opml::parse(""), Err(ImportError::NotOpml)));
+    assert!(matches!(
+        opml::parse("<opml><body></outline></body></opml>"),
+        Err(ImportError::Xml(_))
+    ));
+}
+
+fn feed(url: &str, name: &str, site: Option<&str>, kind: Option<&str>) -> Feed {
+    let mut feed = Feed::new(url.to_owned(), name.to_owned(), 0);
+    feed.site = site.map(ToOwned::to_owned);
+    feed.kind = kind.map(ToOwned::to_owned);
+    feed
+}
+
+fn subscriptions() -> Vec<Folder> {
+    vec![
+        Folder {
+            id: 1,
+            name: "My Folder".to_owned(),
+            feeds: Some(vec![feed(
+                "https://example.com/feed.xml",
+                "Example",
+                Some("https://example.com/"),
+                Some("RSS2"),
+            )]),
+        },
+        Folder {
+            id: 2,
+            name: "News & Views".to_owned(),
+            feeds: Some(vec![
+                feed(
+                    "https://example.org/atom.xml?a=1&b=2",
+                    "\"Quoted\" <Atom>",
+                    None,
+                    Some("Atom"),
+                ),
+                feed("https://example.net/feed.json", "Json", None, Some("JSON")),
+            ]),
+        },
+        Folder {
+            id: 3,
+            name: "Empty".to_owned(),
+            feeds: None,
+        },
+    ]
+}
+
+#[test]
+fn export_attributes() -> Result<()> {
+    let xml = opml::export(&subscriptions())?;
+
+    assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(xml.contains(r#"<opml version="2.0">"#));
+    assert!(xml.contains(
+        r#"<outline type="rss" text="Example" title="Example" xmlUrl="https://example.com/feed.xml" htmlUrl="https://example.com/"/>"#
+    ));
+    assert!(xml.contains(r#"type="atom" text="&quot;Quoted&quot; &lt;Atom&gt;""#));
+    assert!(xml.contains(r#"xmlUrl="https://example.org/atom.xml?a=1&amp;b=2""#));
+    assert!(xml.contains(r#"type="json""#));
+    assert!(xml.contains(r#"<outline text="Empty" title="Empty"/>"#));
+    // the default folder is not a group
+    assert!(!xml.contains(r#"text="My Folder""#));
+    Ok(())
+}
+
+#[test]
+fn export_then_parse() -> Result<()> {
+    let opml = opml::parse(&opml::export(&subscriptions())?)?;
+
+    assert_eq!(
+        folders(&opml),
+        [
+            ("", vec!["https://example.com/feed.xml"]),
+            (
+                "News & Views",
+                vec![
+                    "https://example.org/atom.xml?a=1&b=2",
+                    "https://example.net/feed.json"
+                ]
+            ),
+        ]
+    );
+    assert_eq!(
+        opml.folders[1].feeds.as_ref().unwrap()[0].name,
+        "\"Quoted\" <Atom>"
+    );
+    Ok(())
+}
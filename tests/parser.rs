@@ -1,8 +1,40 @@
-use std::{clone, fs, io::Write, os::unix::prelude::FileExt};
+use std::{fs, sync::Arc};
 
 use anyhow::Result;
-use pindash_news::easymark;
-use pulldown_cmark::Event;
+use pindash_news::easymark::{self, Block, Image, Inline, Marks};
+use pulldown_cmark::{Alignment, Event, LinkType, Tag};
+
+fn parse(content: impl AsRef<str>) -> Vec<Block> {
+    let mut events = Vec::new();
+    easymark::parser(content, &mut events);
+    easymark::build(&events)
+}
+
+fn text(text: &str) -> Inline {
+    Inline::Text(text.to_owned(), Marks::default())
+}
+
+fn strong(text: &str) -> Inline {
+    Inline::Text(
+        text.to_owned(),
+        Marks {
+            strong: true,
+            ..Default::default()
+        },
+    )
+}
+
+fn link(href: &str, content: Vec<Inline>) -> Inline {
+    Inline::Link {
+        href: href.to_owned(),
+        title: String::new(),
+        content,
+    }
+}
+
+fn item(text: &str) -> Vec<Block> {
+    vec![Block::Paragraph(vec![self::text(text)])]
+}
 
 // https://commonmark.org/help/
 // https://docs.github.com/en/get-started/writing-on-github/getting-started-with-writing-and-formatting-on-github/basic-writing-and-formatting-syntax
@@ -19,65 +51,153 @@ fn parse_entity() -> Result<()> {
         .and_then(|c| c.body.clone())
         .unwrap_or_default();
 
-    let mut events = Vec::new();
-    easymark::parser(content, &mut events);
-
-    dbg!(events);
+    let blocks = parse(content);
+    assert!(blocks
+        .iter()
+        .any(|block| matches!(block, Block::Paragraph(_))));
     Ok(())
 }
 
 #[test]
 fn parse_simple() -> Result<()> {
-    let content = include_str!("fixtures/simple.html");
+    let blocks = parse(include_str!("fixtures/simple.html"));
 
-    let mut events = Vec::new();
-    easymark::parser(content, &mut events);
+    for level in 1..=6 {
+        assert_eq!(
+            blocks[level - 1],
+            Block::Heading(level as u8, vec![text(&format!("Heading level {level}"))])
+        );
+    }
+    assert_eq!(blocks[6], Block::Paragraph(vec![strong("Bold")]));
+    assert_eq!(
+        blocks[7],
+        Block::Paragraph(vec![Inline::Text(
+            "Italic".to_owned(),
+            Marks {
+                italics: true,
+                ..Default::default()
+            }
+        )])
+    );
+    assert_eq!(
+        blocks[8],
+        Block::Paragraph(vec![
+            link("https://viz.rs/", vec![Inline::Code("Links".to_owned())]),
+            Inline::Code("Rust".to_owned()),
+        ])
+    );
+
+    let Block::List(None, items) = &blocks[10] else {
+        panic!("not a list: {:?}", blocks[10]);
+    };
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[0], item("Item 1"));
+    let Block::List(Some(1), nested) = &items[1][1] else {
+        panic!("not an ordered list: {:?}", items[1][1]);
+    };
+    assert_eq!(nested[0], item("Item 2-1"));
 
-    dbg!(events);
+    assert!(matches!(&blocks[11], Block::Quote(quote) if quote.len() == 6));
+    assert_eq!(
+        blocks[15],
+        Block::Code {
+            lang: Some("markdoc".to_owned()),
+            text: "Code fences".to_owned(),
+        }
+    );
     Ok(())
 }
 
 #[test]
 fn parse_blockquote() -> Result<()> {
-    let content = include_str!("fixtures/blockquote.html");
-
-    let mut events = Vec::new();
-    easymark::parser(content, &mut events);
+    let blocks = parse(include_str!("fixtures/blockquote.html"));
 
-    dbg!(events);
+    let [Block::Quote(quote)] = blocks.as_slice() else {
+        panic!("not a quote: {blocks:?}");
+    };
+    assert_eq!(quote.len(), 3);
+    assert_eq!(
+        quote[0],
+        Block::Paragraph(vec![
+            text("(…) as much as i dislike the "),
+            link(
+                "https://crates.io/crates/cargo-geiger",
+                vec![text("cargo-geiger")]
+            ),
+            text(" concept, the name … kind of works"),
+        ])
+    );
+    assert!(
+        matches!(&quote[1], Block::Paragraph(inlines) if inlines[0] == Inline::Code("unsafe".to_owned()))
+    );
     Ok(())
 }
 
 #[test]
 fn parse_list() -> Result<()> {
-    let content = include_str!("fixtures/list.html");
-
-    let mut events = Vec::new();
-    easymark::parser(content, &mut events);
+    let blocks = parse(include_str!("fixtures/list.html"));
 
-    dbg!(events);
+    let [Block::List(None, items)] = blocks.as_slice() else {
+        panic!("not a list: {blocks:?}");
+    };
+    assert_eq!(items.len(), 3);
+    assert_eq!(
+        items[0],
+        vec![
+            Block::Paragraph(vec![
+                text("2023-03-08 | Virtual (Boulder, CO, US) | "),
+                link(
+                    "https://www.meetup.com/boulder-elixir-rust/",
+                    vec![text("Boulder Elixir and Rust")]
+                ),
+            ]),
+            Block::List(
+                None,
+                vec![vec![Block::Paragraph(vec![link(
+                    "https://www.meetup.com/boulder-elixir-rust/events/zvxcsryfcfblb/",
+                    vec![strong("Monthly Meetup")]
+                )])]]
+            ),
+        ]
+    );
     Ok(())
 }
 
 #[test]
 fn parse_code() -> Result<()> {
-    let content = include_str!("fixtures/code.html");
-
-    let mut events = Vec::new();
-    easymark::parser(content, &mut events);
+    let blocks = parse(include_str!("fixtures/code.html"));
 
-    dbg!(events);
+    assert_eq!(
+        blocks,
+        vec![Block::Paragraph(vec![link(
+            "https://github.com/rust-lang/rust/pull/97594",
+            vec![
+                text("Implement tuple<->array convertions via "),
+                Inline::Code("From".to_owned()),
+            ]
+        )])]
+    );
     Ok(())
 }
 
 #[test]
 fn parse_h_list() -> Result<()> {
-    let content = include_str!("fixtures/h_list.html");
-
-    let mut events = Vec::new();
-    easymark::parser(content, &mut events);
+    let blocks = parse(include_str!("fixtures/h_list.html"));
 
-    dbg!(events);
+    assert_eq!(
+        blocks[0],
+        Block::Heading(3, vec![link("#foundation", vec![text("Foundation")])])
+    );
+    let Block::List(None, items) = &blocks[1] else {
+        panic!("not a list: {:?}", blocks[1]);
+    };
+    assert_eq!(
+        items[0],
+        vec![Block::Paragraph(vec![link(
+            "https://foundation.rust-lang.org/news/rustconf-2023-is-coming-submit-a-talk-today/",
+            vec![text("RustConf 2023 is Coming—Submit a Talk Today!")]
+        )])]
+    );
     Ok(())
 }
 
@@ -85,13 +205,8 @@ fn parse_h_list() -> Result<()> {
 fn parse_escape() -> Result<()> {
     let content = include_str!("fixtures/haskellweekly.atom");
 
-    let mut events = Vec::new();
-    easymark::parser(
-        dbg!(htmlize::unescape_in(content, htmlize::Context::Attribute)),
-        &mut events,
-    );
-
-    dbg!(events);
+    let blocks = parse(htmlize::unescape_in(content, htmlize::Context::Attribute));
+    assert!(!blocks.is_empty());
     Ok(())
 }
 
@@ -99,12 +214,124 @@ fn parse_escape() -> Result<()> {
 fn parse_blog_rust_lang_org() -> Result<()> {
     let content = include_str!("fixtures/blog.rust-lang.org.raw");
 
-    let mut events = Vec::new();
-    easymark::parser(
-        dbg!(htmlize::unescape_in(content, htmlize::Context::Attribute)),
-        &mut events,
-    );
+    let blocks = parse(htmlize::unescape_in(content, htmlize::Context::Attribute));
+    assert!(!blocks.is_empty());
+    Ok(())
+}
+
+#[test]
+fn parse_figures() -> Result<()> {
+    let blocks = parse(r#"<p>before</p><img src="y.png" alt="figure"><img src="z.png">"#);
 
-    dbg!(events);
+    assert_eq!(
+        blocks,
+        vec![
+            Block::Paragraph(vec![text("before")]),
+            Block::Figure(Image {
+                url: "y.png".to_owned(),
+                alt: "figure".to_owned(),
+            }),
+            Block::Figure(Image {
+                url: "z.png".to_owned(),
+                alt: String::new(),
+            }),
+        ]
+    );
     Ok(())
 }
+
+#[test]
+fn build_images_and_breaks_in_links() {
+    let href = Tag::Link(LinkType::Inline, "https://a.com".into(), "".into());
+    // the order of the parser, alt text before the source
+    let image = Tag::Image(LinkType::Inline, "logo".into(), "x.png".into());
+    let events = [
+        Event::Start(Tag::Paragraph),
+        Event::Start(href.clone()),
+        Event::Start(image.clone()),
+        Event::End(image),
+        Event::Text("line".into()),
+        Event::HardBreak,
+        Event::Text("next".into()),
+        Event::End(href),
+        Event::Text(" tail".into()),
+        Event::End(Tag::Paragraph),
+    ];
+
+    assert_eq!(
+        easymark::build(&events),
+        vec![Block::Paragraph(vec![
+            link(
+                "https://a.com",
+                vec![
+                    Inline::Image(Image {
+                        url: "x.png".to_owned(),
+                        alt: "logo".to_owned(),
+                    }),
+                    text("line"),
+                    Inline::Break,
+                    text("next"),
+                ]
+            ),
+            text(" tail"),
+        ])]
+    );
+}
+
+#[test]
+fn build_tables_and_skip_unbalanced_events() {
+    let cell = |text: &'static str| {
+        [
+            Event::Start(Tag::TableCell),
+            Event::Text(text.into()),
+            Event::End(Tag::TableCell),
+        ]
+    };
+    let mut events = vec![
+        Event::End(Tag::Paragraph),
+        Event::Start(Tag::Table(vec![Alignment::None, Alignment::None])),
+        Event::Start(Tag::TableHead),
+    ];
+    events.extend(cell("name"));
+    events.extend(cell("stars"));
+    events.push(Event::End(Tag::TableHead));
+    events.push(Event::Start(Tag::TableRow));
+    events.extend(cell("news"));
+    events.extend(cell("42"));
+    events.push(Event::End(Tag::TableRow));
+    events.push(Event::End(Tag::Table(vec![])));
+    events.push(Event::End(Tag::Link(
+        LinkType::Inline,
+        "".into(),
+        "".into(),
+    )));
+    events.push(Event::Text("after".into()));
+
+    assert_eq!(
+        easymark::build(&events),
+        vec![
+            Block::Table {
+                head: vec![vec![text("name")], vec![text("stars")]],
+                rows: vec![vec![vec![text("news")], vec![text("42")]]],
+            },
+            Block::Paragraph(vec![text("after")]),
+        ]
+    );
+}
+
+#[test]
+fn documents_are_parsed_again_once_changed() {
+    let mut documents = easymark::Documents::default();
+    let content = include_str!("fixtures/simple.html");
+    assert!(documents.get(1, content).is_none());
+
+    let document = easymark::Document::parse(1, content);
+    assert!(!document.blocks.is_empty());
+    documents.insert(document.clone());
+    let cached = documents.get(1, content).unwrap();
+    assert!(Arc::ptr_eq(&cached.blocks, &document.blocks));
+
+    // the content changed after a fetch
+    assert!(documents.get(1, "<p>updated</p>").is_none());
+    assert!(documents.get(2, content).is_none());
+}
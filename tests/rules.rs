@@ -0,0 +1,111 @@
+use pindash_news::rules::{self, Action, Candidate, Condition, Outcome, Rule};
+
+fn candidate() -> Candidate<'static> {
+    Candidate {
+        feed_id: 2,
+        title: "Announcing Rust 1.70",
+        content: "<p>The Rust team is <b>happy</b> to announce</p>",
+        url: "https://blog.rust-lang.org/2023/06/01/Rust-1.70.0.html",
+        authors: vec!["The Rust Release Team"],
+        categories: vec!["Releases"],
+    }
+}
+
+fn rule(conditions: Vec<Condition>, actions: Vec<Action>) -> Rule {
+    Rule {
+        name: "rule".to_owned(),
+        conditions,
+        actions,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn conditions_match_ignoring_case() {
+    let candidate = candidate();
+    for condition in [
+        Condition::Title("rust 1.70".to_owned()),
+        Condition::Content("team is happy".to_owned()),
+        Condition::Author("release team".to_owned()),
+        Condition::Url("rust-lang.org".to_owned()),
+        Condition::Category("RELEASE".to_owned()),
+        Condition::Feed(2),
+    ] {
+        assert!(condition.matches(&candidate), "{condition:?}");
+    }
+    for condition in [
+        Condition::Title("go".to_owned()),
+        // html is not matched
+        Condition::Content("<b>".to_owned()),
+        Condition::Category(" ".to_owned()),
+        Condition::Feed(3),
+    ] {
+        assert!(!condition.matches(&candidate), "{condition:?}");
+    }
+}
+
+#[test]
+fn rules_need_every_condition() {
+    let candidate = candidate();
+    assert!(!rule(vec![], vec![Action::Star]).matches(&candidate));
+    assert!(rule(
+        vec![Condition::Feed(2), Condition::Title("rust".to_owned())],
+        vec![Action::Star]
+    )
+    .matches(&candidate));
+    assert!(!rule(
+        vec![Condition::Feed(2), Condition::Title("go".to_owned())],
+        vec![Action::Star]
+    )
+    .matches(&candidate));
+}
+
+#[test]
+fn apply_enabled_rules_in_order() {
+    let candidate = candidate();
+    let mut disabled = rule(vec![Condition::Feed(2)], vec![Action::Drop]);
+    disabled.enabled = false;
+    let rules = [
+        disabled,
+        rule(
+            vec![Condition::Feed(2)],
+            vec![Action::Tag("Rust".to_owned()), Action::Folder(4)],
+        ),
+        rule(
+            vec![Condition::Category("releases".to_owned())],
+            vec![
+                Action::Star,
+                Action::Tag("rust".to_owned()),
+                Action::Tag("News".to_owned()),
+                Action::Folder(5),
+            ],
+        ),
+        rule(vec![Condition::Feed(3)], vec![Action::MarkRead]),
+    ];
+    assert_eq!(
+        rules::apply(&rules, &candidate),
+        Outcome {
+            drop: false,
+            read: false,
+            star: true,
+            tags: vec!["Rust".to_owned(), "News".to_owned()],
+            folder: Some(4),
+        }
+    );
+}
+
+#[test]
+fn conditions_and_actions_as_json() {
+    let rule = rule(
+        vec![Condition::Url("example.com".to_owned()), Condition::Feed(1)],
+        vec![Action::MarkRead, Action::Tag("later".to_owned())],
+    );
+    assert_eq!(
+        serde_json::to_string(&rule.conditions).unwrap(),
+        r#"[{"field":"url","value":"example.com"},{"field":"feed","value":1}]"#
+    );
+    assert_eq!(
+        serde_json::to_string(&rule.actions).unwrap(),
+        r#"[{"action":"mark_read"},{"action":"tag","value":"later"}]"#
+    );
+}
@@ -0,0 +1,55 @@
+use pindash_news::search::{self, Filters, Query};
+
+#[test]
+fn parse_words_phrases_and_prefixes() {
+    let query = search::parse(r#"rust  "async closures" tok* "#);
+    assert_eq!(query.text, r#""rust" "async closures" "tok"*"#);
+    assert_eq!(query.filters, Filters::default());
+}
+
+#[test]
+fn parse_qualifiers() {
+    let query =
+        search::parse(r#"release feed:blog folder:"Tech News" author:steve Tag:Later IS:Unread"#);
+    assert_eq!(
+        query,
+        Query {
+            text: r#""release""#.to_owned(),
+            filters: Filters {
+                feed: Some("blog".to_owned()),
+                folder: Some("Tech News".to_owned()),
+                author: Some("steve".to_owned()),
+                tag: Some("Later".to_owned()),
+                unread: true,
+            },
+        }
+    );
+}
+
+#[test]
+fn parse_fts_syntax_as_words() {
+    // operators and unknown qualifiers are matched as words
+    let query = search::parse(r#"NOT a-b is:read say"hi""#);
+    assert_eq!(query.text, r#""NOT" "a-b" "is:read" "say""hi""#);
+    assert!(search::parse(r#"feed: "" *"#).is_empty());
+}
+
+#[test]
+fn highlights_of_a_snippet() {
+    let snippet = format!(
+        "say {}hello{}, {}world{}",
+        search::HIGHLIGHT_START,
+        search::HIGHLIGHT_END,
+        search::HIGHLIGHT_START,
+        search::HIGHLIGHT_END
+    );
+    assert_eq!(
+        search::highlights(&snippet),
+        [
+            ("say ", false),
+            ("hello", true),
+            (", ", false),
+            ("world", true)
+        ]
+    );
+}
@@ -0,0 +1,1883 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use anyhow::Result;
+use pindash_news::{
+    db,
+    errors::{FeedError, FetchError, FolderError, ImageError, ImportError, RuleError},
+    images::ImageCache,
+    models::{
+        ArticleSummary, Feed, FeedSettings, FeedType, FetchState, Folder, Level, Progress,
+        Retention, Scope, SmartFilter, SmartFolder, Timeline,
+    },
+    opml::ImportReport,
+    rules::{Action, Condition, Rule},
+    search,
+    service::{Command, Worker},
+    Store,
+};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// A store backed by a worker and a fresh database, removed on drop.
+struct Fixture {
+    dir: PathBuf,
+    pool: Pool<SqliteConnectionManager>,
+    store: Store,
+}
+
+impl Fixture {
+    /// Failed requests are not retried.
+    fn new(name: &str) -> Result<Self> {
+        Self::with_settings(name, &[(db::FETCH_RETRIES, 0)])
+    }
+
+    fn with_settings(name: &str, settings: &[(&str, u32)]) -> Result<Self> {
+        let dir = env::temp_dir().join(format!("pindash-news-{}-{name}", process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let folders = Arc::new(RwLock::new(Vec::new()));
+        let progress = Arc::new(RwLock::new(Progress::default()));
+        let notifications = Arc::new(RwLock::new(Vec::new()));
+        let smart_folders = Arc::new(RwLock::new(Vec::new()));
+        let tags = Arc::new(RwLock::new(Vec::new()));
+        let pool = db::init(dir.clone(), folders.clone())?;
+        for (key, value) in settings {
+            db::set_setting(&mut pool.get()?, key, value)?;
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let worker = Worker::new(
+            pool.clone(),
+            folders.clone(),
+            smart_folders.clone(),
+            tags.clone(),
+            progress.clone(),
+            notifications.clone(),
+            ImageCache::new(dir.join("images")),
+        );
+        tokio::task::spawn(worker.run(rx));
+
+        Ok(Self {
+            dir,
+            pool,
+            store: Store::new(tx, folders, smart_folders, tags, progress, notifications),
+        })
+    }
+
+    fn feeds(&self, folder_id: u64) -> Vec<Feed> {
+        self.store
+            .folders
+            .read()
+            .unwrap()
+            .iter()
+            .find(|f| f.id == folder_id)
+            .and_then(|f| f.feeds.clone())
+            .unwrap_or_default()
+    }
+
+    /// The first page of a feed, the latest first.
+    async fn articles(&self, feed_id: u64) -> Result<Vec<ArticleSummary>> {
+        Ok(self
+            .store
+            .send(|reply| Command::Timeline(Timeline::Feed(feed_id), None, 50, reply))
+            .recv()
+            .await?)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn folder(name: &str) -> Folder {
+    Folder {
+        name: name.to_owned(),
+        ..Default::default()
+    }
+}
+
+const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>Example</title>
+    <link>https://example.com/</link>
+    <description>An example feed</description>
+    <item>
+      <title>Hello</title>
+      <link>https://example.com/hello</link>
+      <pubDate>Mon, 01 Jan 2024 00:00:00 GMT</pubDate>
+      <description>Hello, world!</description>
+    </item>
+  </channel>
+</rss>"#;
+
+/// Answers each connection with the next response, returns the feed url and
+/// the number of served requests.
+async fn serve(
+    responses: Vec<impl Into<Vec<u8>> + Send + 'static>,
+) -> Result<(String, Arc<AtomicUsize>)> {
+    let (url, served, _) = record(responses).await?;
+    Ok((url, served))
+}
+
+/// Like `serve`, the heads of the served requests are kept too.
+async fn record(
+    responses: Vec<impl Into<Vec<u8>> + Send + 'static>,
+) -> Result<(String, Arc<AtomicUsize>, Arc<Mutex<Vec<String>>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/feed.xml", listener.local_addr()?);
+    let served = Arc::new(AtomicUsize::new(0));
+    let counter = served.clone();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let heads = requests.clone();
+    tokio::task::spawn(async move {
+        for response in responses {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buf = vec![0; 4096];
+            let mut read = 0;
+            while !buf[..read].windows(4).any(|w| w == b"\r\n\r\n") {
+                match stream.read(&mut buf[read..]).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => read += n,
+                }
+            }
+            counter.fetch_add(1, Ordering::SeqCst);
+            heads
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(&buf[..read]).to_lowercase());
+            let _ = stream.write_all(&response.into()).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+    Ok((url, served, requests))
+}
+
+fn response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Feeds are fetched once when they are created.
+fn rss() -> String {
+    response("200 OK", "Content-Type: application/rss+xml\r\n", RSS)
+}
+
+#[tokio::test]
+async fn commands_in_the_same_frame() -> Result<()> {
+    let fixture = Fixture::new("same-frame")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss()]).await?;
+
+    let folder = store.send(|reply| Command::CreateFolder(folder("News"), reply));
+    let feed = store.send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), 1), reply));
+
+    let folder = folder.recv().await?;
+    let feed = feed.recv().await?;
+
+    assert!(folder.id > 0);
+    assert!(feed.id > 0);
+    assert!(store
+        .folders
+        .read()
+        .unwrap()
+        .iter()
+        .any(|f| f.id == folder.id && f.name == "News"));
+    assert!(fixture.feeds(1).iter().any(|f| f.id == feed.id));
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_feed_with_duplicate_url() -> Result<()> {
+    let fixture = Fixture::new("duplicate-url")?;
+    let store = &fixture.store;
+    let (url, served) = serve(vec![rss(), rss()]).await?;
+    let feed = Feed::new(url.clone(), "Example".into(), 1);
+
+    let first = store.send(|reply| Command::CreateFeed(feed.clone(), reply));
+    assert!(first.recv().await.is_ok());
+    let second = store.send(|reply| Command::CreateFeed(feed, reply));
+    assert!(matches!(
+        second.recv().await,
+        Err(FeedError::SubscribedAs(name)) if name == "Example"
+    ));
+
+    // the duplicate is not fetched
+    assert_eq!(served.load(Ordering::SeqCst), 1);
+    assert_eq!(fixture.feeds(1).iter().filter(|f| f.url == url).count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn update_feed_moves_it_to_another_folder() -> Result<()> {
+    let fixture = Fixture::new("move-feed")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss(), rss()]).await?;
+
+    let folder = store
+        .send(|reply| Command::CreateFolder(folder("Later"), reply))
+        .recv()
+        .await?;
+    let mut feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), 1), reply))
+        .recv()
+        .await?;
+
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    feed.folder_id = folder.id;
+    feed.name = "Renamed".into();
+    let updated = store
+        .send(|reply| Command::UpdateFeed((&feed).into(), reply))
+        .recv()
+        .await?;
+
+    assert!(fixture.feeds(1).iter().all(|f| f.id != feed.id));
+    let moved = fixture
+        .feeds(folder.id)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(moved, updated);
+    assert_eq!(moved.name, "Renamed");
+    // what the fetch kept is not replaced by the edited settings
+    assert_eq!(moved.unread, 1);
+    assert_eq!(moved.http_status, Some(200));
+    assert!(moved.last_fetched.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn delete_folder_moves_feeds_to_the_default_folder() -> Result<()> {
+    let fixture = Fixture::new("delete-folder")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss()]).await?;
+
+    let folder = store
+        .send(|reply| Command::CreateFolder(folder("Trash"), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), folder.id), reply))
+        .recv()
+        .await?;
+
+    store
+        .send(|reply| Command::DeleteFolder(folder.clone(), reply))
+        .recv()
+        .await?;
+
+    assert!(store
+        .folders
+        .read()
+        .unwrap()
+        .iter()
+        .all(|f| f.id != folder.id));
+    assert!(fixture
+        .feeds(1)
+        .iter()
+        .any(|f| f.id == feed.id && f.folder_id == 1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_folder_with_duplicate_name() -> Result<()> {
+    let fixture = Fixture::new("duplicate-folder")?;
+    let store = &fixture.store;
+
+    let first = store.send(|reply| Command::CreateFolder(folder("Twice"), reply));
+    let second = store.send(|reply| Command::CreateFolder(folder("Twice"), reply));
+
+    assert!(first.recv().await.is_ok());
+    assert!(matches!(
+        second.recv().await,
+        Err(FolderError::AlreadyExists)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_skips_unchanged_feeds_on_304() -> Result<()> {
+    let fixture = Fixture::new("not-modified")?;
+    let store = &fixture.store;
+    let validators = "ETag: \"v1\"\r\nLast-Modified: Mon, 01 Jan 2024 00:00:00 GMT\r\n";
+    let (url, _, requests) = record(vec![
+        rss(),
+        response("200 OK", validators, RSS),
+        response("304 Not Modified", "", ""),
+        response("200 OK", "ETag: \"v2\"\r\n", RSS),
+    ])
+    .await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), 1), reply))
+        .recv()
+        .await?;
+    let fetch = || async {
+        // with the validators of the latest fetch, like the sidebar
+        let current = fixture
+            .feeds(1)
+            .into_iter()
+            .find(|f| f.id == feed.id)
+            .unwrap();
+        store
+            .send(|reply| Command::FetchFeed(current, reply))
+            .recv()
+            .await?;
+        let stored = db::fetch_folders(&mut fixture.pool.get()?)?
+            .into_iter()
+            .filter_map(|f| f.feeds)
+            .flatten()
+            .find(|f| f.id == feed.id)
+            .unwrap();
+        let fetched = fixture
+            .feeds(1)
+            .into_iter()
+            .find(|f| f.id == feed.id)
+            .unwrap();
+        Ok::<_, anyhow::Error>((stored, fetched))
+    };
+
+    let (stored, _) = fetch().await?;
+    assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(fixture.articles(feed.id).await?.len(), 1);
+
+    let (stored, fetched) = fetch().await?;
+    let head = requests.lock().unwrap()[2].clone();
+    assert!(head.contains("if-none-match: \"v1\""));
+    assert!(head.contains("if-modified-since: mon, 01 jan 2024 00:00:00 gmt"));
+    assert_eq!(fetched.state, FetchState::NotModified);
+    assert_eq!(stored.http_status, Some(304));
+    // validators are kept when a 304 omits them
+    assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(fixture.articles(feed.id).await?.len(), 1);
+
+    let (stored, fetched) = fetch().await?;
+    assert_eq!(fetched.state, FetchState::Idle);
+    assert_eq!(stored.http_status, Some(200));
+    assert_eq!(stored.etag.as_deref(), Some("\"v2\""));
+    assert_eq!(
+        stored.last_modified.as_deref(),
+        Some("Mon, 01 Jan 2024 00:00:00 GMT")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_failure_is_kept_and_reported() -> Result<()> {
+    let fixture = Fixture::with_settings(
+        "fetch-failure",
+        &[(db::FETCH_RETRIES, 0), (db::DEAD_AFTER_FAILURES, 2)],
+    )?;
+    let store = &fixture.store;
+    // the server goes away once the feed is created
+    let (url, _) = serve(vec![rss()]).await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Closed".into(), 1), reply))
+        .recv()
+        .await?;
+    for _ in 0..2 {
+        let result = store
+            .send(|reply| Command::FetchFeed(feed.clone(), reply))
+            .recv()
+            .await;
+        assert!(matches!(result, Err(FetchError::Request(_))));
+    }
+    // each refresh started by the user is a round of its own
+    assert_eq!(
+        *store.progress.read().unwrap(),
+        Progress {
+            total: 1,
+            done: 1,
+            failed: 1
+        }
+    );
+
+    let failed = fixture
+        .feeds(1)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert!(matches!(failed.state, FetchState::Failed { .. }));
+    assert!(failed.last_error.is_some());
+    assert_eq!(failed.failures, 2);
+    assert!(failed.dead);
+
+    let stored = db::fetch_folders(&mut fixture.pool.get()?)?
+        .into_iter()
+        .filter_map(|f| f.feeds)
+        .flatten()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(stored.last_error, failed.last_error);
+    assert_eq!(stored.failures, 2);
+    assert!(stored.dead);
+    assert_eq!(stored.state, FetchState::Idle);
+
+    let notifications = store.notifications.read().unwrap();
+    assert_eq!(notifications.len(), 3);
+    assert_eq!(notifications[0].level, Level::Error);
+    assert!(notifications[0].text.starts_with("Closed failed: "));
+    assert!(notifications
+        .iter()
+        .any(|n| n.text == "Closed stopped refreshing after 2 failures"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_retries_after_retry_after() -> Result<()> {
+    let fixture = Fixture::with_settings("fetch-retry", &[(db::FETCH_RETRIES, 2)])?;
+    let store = &fixture.store;
+    let (url, served) = serve(vec![
+        rss(),
+        response("503 Service Unavailable", "Retry-After: 0\r\n", ""),
+        response("429 Too Many Requests", "Retry-After: 0\r\n", ""),
+        response("200 OK", "Content-Type: application/rss+xml\r\n", RSS),
+    ])
+    .await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Flaky".into(), 1), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+
+    assert_eq!(served.load(Ordering::SeqCst), 4);
+    let fetched = fixture
+        .feeds(1)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(fetched.state, FetchState::Idle);
+    assert_eq!(fetched.http_status, Some(200));
+    assert_eq!(fetched.failures, 0);
+    assert!(fetched.last_fetched.is_some());
+    assert_eq!(
+        fixture
+            .articles(feed.id)
+            .await?
+            .iter()
+            .map(|a| a.title.as_str())
+            .collect::<Vec<_>>(),
+        ["Hello"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_waits_for_a_long_retry_after() -> Result<()> {
+    let fixture = Fixture::with_settings("fetch-retry-later", &[(db::FETCH_RETRIES, 2)])?;
+    let store = &fixture.store;
+    let (url, served) = serve(vec![
+        rss(),
+        response("503 Service Unavailable", "Retry-After: 3600\r\n", ""),
+        response("200 OK", "Content-Type: application/rss+xml\r\n", RSS),
+    ])
+    .await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Busy".into(), 1), reply))
+        .recv()
+        .await?;
+    let result = store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await;
+    // not retried, the deadline is kept for the scheduler
+    assert!(matches!(result, Err(FetchError::RetryAfter(_, d)) if d.as_secs() == 3600));
+    assert_eq!(served.load(Ordering::SeqCst), 2);
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let waiting = fixture
+        .feeds(1)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    let retry_at = waiting.retry_at.unwrap();
+    assert!(retry_at > now + 3_500_000 && retry_at <= now + 3_600_000);
+    assert_eq!(waiting.http_status, Some(503));
+    let stored = db::fetch_folders(&mut fixture.pool.get()?)?
+        .into_iter()
+        .filter_map(|f| f.feeds)
+        .flatten()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(stored.retry_at, Some(retry_at));
+
+    // a refresh by the user is not held back, and clears it
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(fixture.feeds(1)[0].retry_at, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_gives_up_after_retries() -> Result<()> {
+    let fixture = Fixture::with_settings("fetch-give-up", &[(db::FETCH_RETRIES, 1)])?;
+    let store = &fixture.store;
+    let (url, served) = serve(vec![
+        rss(),
+        response("500 Internal Server Error", "Retry-After: 0\r\n", ""),
+        response("502 Bad Gateway", "Retry-After: 0\r\n", ""),
+        response("200 OK", "", RSS),
+    ])
+    .await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Down".into(), 1), reply))
+        .recv()
+        .await?;
+    let result = store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await;
+
+    assert!(matches!(result, Err(FetchError::Status(status)) if status.as_u16() == 502));
+    assert_eq!(served.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_keeps_permanent_redirects() -> Result<()> {
+    let fixture = Fixture::new("redirect-permanent")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![
+        rss(),
+        response("301 Moved Permanently", "Location: /moved.xml\r\n", ""),
+        response("200 OK", "ETag: \"v1\"\r\n", RSS),
+    ])
+    .await?;
+    let location = url.replace("/feed.xml", "/moved.xml");
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url.clone(), "Moving".into(), 1), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| {
+            Command::UpdateFeed(
+                FeedSettings {
+                    import_categories: true,
+                    keep_days: Some(7),
+                    keep_articles: Some(20),
+                    ..(&feed).into()
+                },
+                reply,
+            )
+        })
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+
+    let moved = fixture
+        .feeds(1)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(moved.url, location);
+    assert_eq!(moved.moved_from.as_ref(), Some(&url));
+    assert_eq!(moved.etag.as_deref(), Some("\"v1\""));
+
+    let stored = db::fetch_folders(&mut fixture.pool.get()?)?
+        .into_iter()
+        .filter_map(|f| f.feeds)
+        .flatten()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(stored.url, location);
+    assert_eq!(stored.moved_from, Some(url.clone()));
+    assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+    // settings of the feed are kept
+    assert!(stored.import_categories);
+    assert_eq!(stored.keep_days, Some(7));
+    assert_eq!(stored.keep_articles, Some(20));
+    assert!(moved.import_categories);
+    assert_eq!(moved.keep_days, Some(7));
+
+    // the old url belongs to the moved feed
+    let result = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Again".into(), 1), reply))
+        .recv()
+        .await;
+    assert!(matches!(result, Err(FeedError::SubscribedAs(name)) if name == "Moving"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_keeps_the_url_when_a_redirect_is_not_a_feed() -> Result<()> {
+    let fixture = Fixture::new("redirect-not-a-feed")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![
+        rss(),
+        response("301 Moved Permanently", "Location: /parked.html\r\n", ""),
+        response(
+            "200 OK",
+            "Content-Type: text/html\r\n",
+            "<html>parked</html>",
+        ),
+    ])
+    .await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url.clone(), "Parked".into(), 1), reply))
+        .recv()
+        .await?;
+    let result = store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await;
+    assert!(matches!(result, Err(FetchError::Parse(_))));
+
+    let failed = fixture
+        .feeds(1)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(failed.url, url);
+    assert_eq!(failed.moved_from, None);
+    let redirects: usize = fixture.pool.get()?.query_row(
+        "SELECT count(*) FROM feed_redirects WHERE feed_id = ?1",
+        [feed.id],
+        |row| row.get(0),
+    )?;
+    assert_eq!(redirects, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_follows_temporary_redirects() -> Result<()> {
+    let fixture = Fixture::new("redirect-temporary")?;
+    let store = &fixture.store;
+    let (url, served) = serve(vec![
+        rss(),
+        response("302 Found", "Location: /elsewhere.xml\r\n", ""),
+        response("200 OK", "", RSS),
+    ])
+    .await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url.clone(), "Visiting".into(), 1), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+
+    assert_eq!(served.load(Ordering::SeqCst), 3);
+    let fetched = fixture
+        .feeds(1)
+        .into_iter()
+        .find(|f| f.id == feed.id)
+        .unwrap();
+    assert_eq!(fetched.url, url);
+    assert_eq!(fetched.moved_from, None);
+    assert_eq!(fixture.articles(feed.id).await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn discover_a_feed_url() -> Result<()> {
+    let fixture = Fixture::new("discover-feed")?;
+    let (url, _) = serve(vec![response(
+        "200 OK",
+        "Content-Type: application/rss+xml; charset=utf-8\r\n",
+        RSS,
+    )])
+    .await?;
+
+    let candidates = fixture
+        .store
+        .send(|reply| Command::Discover(url.clone(), reply))
+        .recv()
+        .await?;
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].url, url);
+    assert_eq!(candidates[0].title.as_deref(), Some("Example"));
+    assert_eq!(candidates[0].kind.as_deref(), Some("application/rss+xml"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn discover_feeds_of_a_page() -> Result<()> {
+    let fixture = Fixture::new("discover-page")?;
+    let html = r#"<html><head>
+        <link rel="alternate" type="application/rss+xml" title="Posts" href="/posts.xml">
+    </head></html>"#;
+    let (url, served) = serve(vec![response(
+        "200 OK",
+        "Content-Type: text/html\r\n",
+        html,
+    )])
+    .await?;
+
+    let candidates = fixture
+        .store
+        .send(|reply| Command::Discover(url.replace("/feed.xml", "/"), reply))
+        .recv()
+        .await?;
+
+    assert_eq!(served.load(Ordering::SeqCst), 1);
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].url, url.replace("/feed.xml", "/posts.xml"));
+    assert_eq!(candidates[0].title.as_deref(), Some("Posts"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn preview_a_feed() -> Result<()> {
+    let fixture = Fixture::new("preview")?;
+    let (url, _) = serve(vec![rss()]).await?;
+
+    let preview = fixture
+        .store
+        .send(|reply| Command::Preview(url.clone(), reply))
+        .recv()
+        .await?;
+
+    assert_eq!(preview.url, url);
+    assert_eq!(preview.title.as_deref(), Some("Example"));
+    assert_eq!(preview.feed_type, FeedType::RSS2);
+    assert_eq!(preview.description.as_deref(), Some("An example feed"));
+    assert_eq!(preview.entries, 1);
+    assert_eq!(preview.latest, ["Hello"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_feed_falls_back_to_its_title() -> Result<()> {
+    let fixture = Fixture::new("fallback-name")?;
+    let (url, _) = serve(vec![rss()]).await?;
+
+    let feed = fixture
+        .store
+        .send(|reply| Command::CreateFeed(Feed::new(url, " ".into(), 1), reply))
+        .recv()
+        .await?;
+
+    assert_eq!(feed.name, "Example");
+    assert!(fixture
+        .feeds(1)
+        .iter()
+        .any(|f| f.id == feed.id && f.name == "Example"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_feed_with_broken_url() -> Result<()> {
+    let fixture = Fixture::new("broken-url")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![
+        response("404 Not Found", "", ""),
+        response("200 OK", "Content-Type: text/html\r\n", "<html></html>"),
+    ])
+    .await?;
+
+    let missing = store
+        .send(|reply| Command::CreateFeed(Feed::new(url.clone(), "".into(), 1), reply))
+        .recv()
+        .await;
+    assert!(matches!(
+        missing,
+        Err(FeedError::Fetch(FetchError::Status(status))) if status.as_u16() == 404
+    ));
+    let page = store
+        .send(|reply| Command::CreateFeed(Feed::new(url.clone(), "".into(), 1), reply))
+        .recv()
+        .await;
+    assert!(matches!(page, Err(FeedError::Fetch(FetchError::Parse(_)))));
+
+    assert!(fixture.feeds(1).iter().all(|f| f.url != url));
+    assert!(db::fetch_folders(&mut fixture.pool.get()?)?
+        .into_iter()
+        .filter_map(|f| f.feeds)
+        .flatten()
+        .all(|f| f.url != url));
+    Ok(())
+}
+
+#[tokio::test]
+async fn import_opml_into_folders() -> Result<()> {
+    let fixture = Fixture::new("import-opml")?;
+    let store = &fixture.store;
+    let path = PathBuf::from("tests/fixtures/subscriptions.opml");
+
+    let rust = store
+        .send(|reply| Command::CreateFolder(folder("Rust"), reply))
+        .recv()
+        .await?;
+    let report = store
+        .send(|reply| Command::ImportOpml(path.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(
+        report,
+        ImportReport {
+            added: 5,
+            skipped: 0,
+            invalid: 2,
+        }
+    );
+
+    // folder names with the number of their feeds
+    let names = |folders: &[Folder]| {
+        folders
+            .iter()
+            .map(|f| (f.name.clone(), f.feeds.as_ref().map_or(0, Vec::len)))
+            .collect::<Vec<_>>()
+    };
+    let folders = store.folders.read().unwrap().clone();
+    // an existing folder is reused, the others are created
+    assert_eq!(folders.iter().filter(|f| f.name == "Rust").count(), 1);
+    assert_eq!(fixture.feeds(rust.id).len(), 2);
+    assert!(folders
+        .iter()
+        .any(|f| f.name == "Nightly" && f.feeds.as_ref().map_or(0, Vec::len) == 1));
+    assert!(fixture
+        .feeds(1)
+        .iter()
+        .any(|f| f.url == "https://example.com/feed.xml"));
+    let stored = db::fetch_folders(&mut fixture.pool.get()?)?;
+    assert_eq!(names(&stored), names(&folders));
+
+    let report = store
+        .send(|reply| Command::ImportOpml(path, reply))
+        .recv()
+        .await?;
+    assert_eq!(
+        report,
+        ImportReport {
+            added: 0,
+            skipped: 5,
+            invalid: 2,
+        }
+    );
+    assert_eq!(names(&store.folders.read().unwrap()), names(&folders));
+    Ok(())
+}
+
+#[tokio::test]
+async fn import_opml_which_is_not_opml() -> Result<()> {
+    let fixture = Fixture::new("import-not-opml")?;
+
+    let result = fixture
+        .store
+        .send(|reply| Command::ImportOpml("tests/fixtures/discovery.html".into(), reply))
+        .recv()
+        .await;
+
+    assert!(matches!(result, Err(ImportError::NotOpml)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn export_opml_then_import_it() -> Result<()> {
+    let fixture = Fixture::new("export-opml")?;
+    let store = &fixture.store;
+    let path = fixture.dir.join("subscriptions.opml");
+
+    store
+        .send(|reply| Command::ImportOpml("tests/fixtures/subscriptions.opml".into(), reply))
+        .recv()
+        .await?;
+    let exported = store
+        .send(|reply| Command::ExportOpml(path.clone(), reply))
+        .recv()
+        .await?;
+    let feeds = fixture
+        .store
+        .folders
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|f| f.feeds.as_ref())
+        .map(Vec::len)
+        .sum::<usize>();
+    assert_eq!(exported, feeds);
+
+    // everything is subscribed already
+    let report = store
+        .send(|reply| Command::ImportOpml(path, reply))
+        .recv()
+        .await?;
+    assert_eq!(
+        report,
+        ImportReport {
+            added: 0,
+            skipped: feeds,
+            invalid: 0,
+        }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn mark_articles_as_read() -> Result<()> {
+    let fixture = Fixture::new("mark-read")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss(), rss(), rss()]).await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), 1), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let article = fixture.articles(feed.id).await?.remove(0);
+    assert!(!article.read);
+    let id = article.id;
+    let stored = || store.send(move |reply| Command::Article(id, reply)).recv();
+
+    // published in 2024
+    let before = 1_577_836_800_000; // 2020-01-01
+    let changed = store
+        .send(|reply| Command::MarkRead(Scope::Feed(feed.id), Some(before), reply))
+        .recv()
+        .await?;
+    assert_eq!(changed, 0);
+    assert!(!stored().await?.read);
+
+    let changed = store
+        .send(|reply| Command::MarkRead(Scope::Folder(1), None, reply))
+        .recv()
+        .await?;
+    assert_eq!(changed, 1);
+    assert!(fixture.articles(feed.id).await?[0].read);
+    assert!(stored().await?.read_at.is_some());
+
+    // a refresh keeps it read
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    assert!(stored().await?.read);
+
+    store
+        .send(|reply| Command::MarkUnread(article, reply))
+        .recv()
+        .await?;
+    assert!(!fixture.articles(feed.id).await?[0].read);
+    assert_eq!(stored().await?.read_at, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unread_counts_follow_fetches_and_reads() -> Result<()> {
+    let fixture = Fixture::new("unread-counts")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss(), rss()]).await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), 1), reply))
+        .recv()
+        .await?;
+    let unread = || {
+        let folders = store.folders.read().unwrap();
+        let folder = folders.iter().find(|f| f.id == 1).unwrap();
+        let feed = folder
+            .feeds
+            .iter()
+            .flatten()
+            .find(|f| f.id == feed.id)
+            .unwrap();
+        (feed.unread, folder.unread())
+    };
+    assert_eq!(unread(), (0, 0));
+
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(unread(), (1, 1));
+    let stored = db::fetch_folders(&mut fixture.pool.get()?)?
+        .into_iter()
+        .find(|f| f.id == 1)
+        .unwrap();
+    assert_eq!(stored.unread(), 1);
+
+    let article = fixture.articles(feed.id).await?.remove(0);
+    store
+        .send(|reply| Command::MarkRead(Scope::Article(article.id), None, reply))
+        .recv()
+        .await?;
+    assert_eq!(unread(), (0, 0));
+
+    // only a read article counts again
+    for _ in 0..2 {
+        store
+            .send(|reply| Command::MarkUnread(article.clone(), reply))
+            .recv()
+            .await?;
+        assert_eq!(unread(), (1, 1));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn star_articles() -> Result<()> {
+    let fixture = Fixture::new("star")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss(), rss(), rss()]).await?;
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), 1), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let id = fixture.articles(feed.id).await?[0].id;
+    let article = || store.send(|reply| Command::Article(id, reply)).recv();
+    assert_eq!(article().await?.starred, None);
+
+    let starred = store
+        .send(|reply| Command::Star(id, true, reply))
+        .recv()
+        .await?;
+    assert!(starred.is_some());
+    assert_eq!(article().await?.starred, starred);
+
+    let list = store
+        .send(|reply| Command::Timeline(Timeline::Starred, None, 50, reply))
+        .recv()
+        .await?;
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].id, id);
+    assert_eq!(list[0].starred, starred);
+
+    // a refresh keeps the star
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let list = store
+        .send(|reply| Command::Timeline(Timeline::Starred, None, 50, reply))
+        .recv()
+        .await?;
+    assert_eq!(list.len(), 1);
+
+    let starred = store
+        .send(|reply| Command::Star(id, false, reply))
+        .recv()
+        .await?;
+    assert_eq!(starred, None);
+    assert_eq!(article().await?.starred, None);
+    let list = store
+        .send(|reply| Command::Timeline(Timeline::Starred, None, 50, reply))
+        .recv()
+        .await?;
+    assert!(list.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn timelines_across_feeds() -> Result<()> {
+    let fixture = Fixture::new("timelines")?;
+    let store = &fixture.store;
+    let second = RSS.replace(
+        "</channel>",
+        r#"<item>
+      <title>Again</title>
+      <link>https://example.com/again</link>
+      <pubDate>Tue, 02 Jan 2024 00:00:00 GMT</pubDate>
+    </item>
+  </channel>"#,
+    );
+    let xml = response("200 OK", "Content-Type: application/rss+xml\r\n", &second);
+    let (url, _) = serve(vec![xml.clone(), xml]).await?;
+
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), news.id), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let timeline =
+        |timeline, after| store.send(move |reply| Command::Timeline(timeline, after, 50, reply));
+
+    // the latest first
+    let all = timeline(Timeline::All, None).recv().await?;
+    let titles = all.iter().map(|a| a.title.as_str()).collect::<Vec<_>>();
+    assert_eq!(titles, ["Again", "Hello"]);
+    assert_eq!(timeline(Timeline::Folder(news.id), None).recv().await?, all);
+    assert!(timeline(Timeline::Folder(1), None).recv().await?.is_empty());
+    // published in 2024
+    assert!(timeline(Timeline::Today, None).recv().await?.is_empty());
+
+    assert_eq!(timeline(Timeline::Feed(feed.id), None).recv().await?, all);
+
+    // the next page starts after the last article
+    let page = timeline(Timeline::All, Some(all[0].cursor(Timeline::All)))
+        .recv()
+        .await?;
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].title, "Hello");
+    let after = Some(page[0].cursor(Timeline::All));
+    assert!(timeline(Timeline::All, after).recv().await?.is_empty());
+
+    store
+        .send(|reply| Command::MarkRead(Scope::Article(all[0].id), None, reply))
+        .recv()
+        .await?;
+    let unread = timeline(Timeline::Unread, None).recv().await?;
+    assert_eq!(unread.len(), 1);
+    assert_eq!(unread[0].title, "Hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_articles() -> Result<()> {
+    let fixture = Fixture::new("search")?;
+    let store = &fixture.store;
+    let body = RSS.replace(
+        "<description>Hello, world!</description>",
+        "<description>&lt;p&gt;Hello, &lt;b&gt;wonderful&lt;/b&gt; world!&lt;/p&gt;</description>",
+    );
+    let xml = response("200 OK", "Content-Type: application/rss+xml\r\n", &body);
+    let (url, _) = serve(vec![xml.clone(), xml.clone(), xml]).await?;
+
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), news.id), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let search = |query: &str| {
+        let query = query.to_owned();
+        store.send(move |reply| Command::Search(query, reply))
+    };
+
+    // html is stripped, the matched words are highlighted
+    let hits = search("wonderful").recv().await?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].article.title, "Hello");
+    assert!(!hits[0].snippet.contains('<'));
+    assert_eq!(
+        search::highlights(&hits[0].snippet)
+            .into_iter()
+            .filter(|(_, matched)| *matched)
+            .collect::<Vec<_>>(),
+        [("wonderful", true)]
+    );
+
+    assert_eq!(search("wonder*").recv().await?.len(), 1);
+    assert_eq!(search(r#""wonderful world""#).recv().await?.len(), 1);
+    assert!(search(r#""world wonderful""#).recv().await?.is_empty());
+    assert!(search("<b>").recv().await?.is_empty());
+    assert_eq!(search("hello feed:exam folder:news").recv().await?.len(), 1);
+    assert!(search("hello folder:other").recv().await?.is_empty());
+    assert!(search("author:nobody").recv().await?.is_empty());
+
+    assert_eq!(search("is:unread").recv().await?.len(), 1);
+    store
+        .send(|reply| Command::MarkRead(Scope::Feed(feed.id), None, reply))
+        .recv()
+        .await?;
+    assert!(search("hello is:unread").recv().await?.is_empty());
+
+    // a refresh keeps a single entry in the index
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(search("hello").recv().await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn smart_folders_filter_articles() -> Result<()> {
+    let fixture = Fixture::new("smart-folders")?;
+    let store = &fixture.store;
+    let (url, _) = serve(vec![rss(), rss()]).await?;
+
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), news.id), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+
+    let smart = |name: &str, filter: SmartFilter| SmartFolder {
+        name: name.to_owned(),
+        filter,
+        ..Default::default()
+    };
+    let unread = |id: u64| {
+        store
+            .smart_folders
+            .read()
+            .unwrap()
+            .iter()
+            .find(|f| f.id == id)
+            .map(|f| f.unread)
+    };
+    let articles =
+        |id: u64| store.send(move |reply| Command::Timeline(Timeline::Smart(id), None, 50, reply));
+
+    let filter = SmartFilter {
+        keywords: "hel*".to_owned(),
+        folders: vec![news.id],
+        read: Some(false),
+        ..Default::default()
+    };
+    let hello = store
+        .send(|reply| Command::CreateSmartFolder(smart("Hello", filter.clone()), reply))
+        .recv()
+        .await?;
+    assert!(hello.id > 0);
+    assert_eq!(unread(hello.id), Some(1));
+    assert_eq!(articles(hello.id).recv().await?.len(), 1);
+
+    let twice = store
+        .send(|reply| Command::CreateSmartFolder(smart("Hello", SmartFilter::default()), reply))
+        .recv()
+        .await;
+    assert!(matches!(twice, Err(FolderError::AlreadyExists)));
+
+    // published at 2024-01-01 00:00 UTC
+    let published = 1_704_067_200_000;
+    for (filter, matches) in [
+        (
+            SmartFilter {
+                feeds: vec![feed.id],
+                ..Default::default()
+            },
+            1,
+        ),
+        (
+            SmartFilter {
+                folders: vec![1],
+                ..Default::default()
+            },
+            0,
+        ),
+        (
+            SmartFilter {
+                authors: vec!["Nobody".to_owned()],
+                ..Default::default()
+            },
+            0,
+        ),
+        (
+            SmartFilter {
+                starred: Some(true),
+                ..Default::default()
+            },
+            0,
+        ),
+        (
+            SmartFilter {
+                since: Some(published),
+                until: Some(published + 1),
+                ..Default::default()
+            },
+            1,
+        ),
+        (
+            SmartFilter {
+                until: Some(published),
+                ..Default::default()
+            },
+            0,
+        ),
+    ] {
+        let folder = store
+            .send(|reply| Command::CreateSmartFolder(smart("Other", filter.clone()), reply))
+            .recv()
+            .await?;
+        let found = articles(folder.id).recv().await?;
+        assert_eq!(found.len(), matches, "{filter:?}");
+        store
+            .send(|reply| Command::DeleteSmartFolder(folder, reply))
+            .recv()
+            .await?;
+    }
+
+    // unread counts follow reads
+    store
+        .send(|reply| Command::MarkRead(Scope::Feed(feed.id), None, reply))
+        .recv()
+        .await?;
+    assert_eq!(unread(hello.id), Some(0));
+    assert!(articles(hello.id).recv().await?.is_empty());
+
+    let mut any = hello.clone();
+    any.filter.read = None;
+    store
+        .send(|reply| Command::UpdateSmartFolder(any, reply))
+        .recv()
+        .await?;
+    assert_eq!(articles(hello.id).recv().await?.len(), 1);
+
+    store
+        .send(|reply| Command::DeleteSmartFolder(hello.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(unread(hello.id), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rules_apply_to_new_articles() -> Result<()> {
+    let fixture = Fixture::new("rules")?;
+    let store = &fixture.store;
+    let body = RSS
+        .replace(
+            "<description>Hello, world!</description>",
+            "<description>Hello, world!</description>\n      <category>Rust</category>",
+        )
+        .replace(
+            "</channel>",
+            r#"<item>
+      <title>Again</title>
+      <link>https://example.com/again</link>
+      <pubDate>Tue, 02 Jan 2024 00:00:00 GMT</pubDate>
+    </item>
+  </channel>"#,
+        );
+    let xml = response("200 OK", "Content-Type: application/rss+xml\r\n", &body);
+    let (url, _) = serve(vec![xml.clone(), xml.clone(), xml]).await?;
+
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    let later = store
+        .send(|reply| Command::CreateFolder(folder("Later"), reply))
+        .recv()
+        .await?;
+
+    let incomplete = store
+        .send(|reply| Command::SaveRule(rule("Nothing", vec![], vec![]), reply))
+        .recv()
+        .await;
+    assert!(matches!(incomplete, Err(RuleError::Incomplete)));
+
+    let drop = rule(
+        "Drop",
+        vec![Condition::Title("again".to_owned())],
+        vec![Action::Drop],
+    );
+    let drop = store
+        .send(|reply| Command::SaveRule(drop, reply))
+        .recv()
+        .await?;
+    assert!(drop.id > 0);
+    let rust = rule(
+        "Rust",
+        vec![Condition::Category("rust".to_owned())],
+        vec![
+            Action::MarkRead,
+            Action::Star,
+            Action::Tag("Rust".to_owned()),
+            Action::Folder(later.id),
+        ],
+    );
+    store
+        .send(|reply| Command::SaveRule(rust.clone(), reply))
+        .recv()
+        .await?;
+    let twice = store
+        .send(|reply| Command::SaveRule(rust.clone(), reply))
+        .recv()
+        .await;
+    assert!(matches!(twice, Err(RuleError::AlreadyExists)));
+    assert_eq!(
+        store
+            .send(Command::Rules)
+            .recv()
+            .await?
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<_>>(),
+        ["Drop", "Rust"]
+    );
+
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), news.id), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let timeline =
+        |timeline, after| store.send(move |reply| Command::Timeline(timeline, after, 50, reply));
+
+    let all = timeline(Timeline::All, None).recv().await?;
+    assert_eq!(all.len(), 1);
+    let hello = &all[0];
+    assert!(hello.read);
+    assert!(hello.starred.is_some());
+    let tags: Vec<String> = fixture
+        .pool
+        .get()?
+        .prepare(
+            "SELECT g.name FROM tags AS g JOIN article_tags AS ag ON ag.g = g.id WHERE ag.t = ?1",
+        )?
+        .query_map([hello.id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    assert_eq!(tags, ["Rust"]);
+    // moved out of the folder of its feed
+    assert!(timeline(Timeline::Folder(news.id), None)
+        .recv()
+        .await?
+        .is_empty());
+    assert_eq!(
+        timeline(Timeline::Folder(later.id), None)
+            .recv()
+            .await?
+            .len(),
+        1
+    );
+    // and read with it
+    store
+        .send(|reply| Command::MarkUnread(hello.clone(), reply))
+        .recv()
+        .await?;
+    let mark_read = |id| store.send(move |reply| Command::MarkRead(Scope::Folder(id), None, reply));
+    assert_eq!(mark_read(news.id).recv().await?, 0);
+    assert_eq!(mark_read(later.id).recv().await?, 1);
+
+    // known articles are left alone
+    store
+        .send(|reply| Command::Star(hello.id, false, reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let all = timeline(Timeline::All, None).recv().await?;
+    assert_eq!(all.len(), 1);
+    assert!(all[0].starred.is_none());
+
+    let dry_run = store
+        .send(|reply| Command::DryRun(rust.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(dry_run.matched, 1);
+    assert_eq!(dry_run.scanned, 1);
+    assert!(!dry_run.truncated);
+    assert_eq!(dry_run.articles[0].title, "Hello");
+    assert!(dry_run.articles[0].content.is_empty());
+    // only the latest articles are looked at
+    let dry_run = db::dry_run_rule(&mut fixture.pool.get()?, &rust, 0, 20)?;
+    assert_eq!((dry_run.scanned, dry_run.matched), (0, 0));
+    assert!(dry_run.truncated);
+    let dry_run = store
+        .send(|reply| Command::DryRun(drop, reply))
+        .recv()
+        .await?;
+    assert_eq!(dry_run.matched, 0);
+    Ok(())
+}
+
+fn rule(name: &str, conditions: Vec<Condition>, actions: Vec<Action>) -> Rule {
+    Rule {
+        name: name.to_owned(),
+        conditions,
+        actions,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn tag_articles_and_import_categories() -> Result<()> {
+    let fixture = Fixture::new("tags")?;
+    let store = &fixture.store;
+    let first = RSS.replace(
+        "<description>Hello, world!</description>",
+        "<description>Hello, world!</description>\n      <category>Rust</category>",
+    );
+    let second = first.replace(
+        "</channel>",
+        r#"<item>
+      <title>Again</title>
+      <link>https://example.com/again</link>
+      <pubDate>Tue, 02 Jan 2024 00:00:00 GMT</pubDate>
+      <category>rust</category>
+      <category>News</category>
+    </item>
+  </channel>"#,
+    );
+    let xml = |body: &str| response("200 OK", "Content-Type: application/rss+xml\r\n", body);
+    let (url, _) = serve(vec![xml(&first), xml(&first), xml(&second)]).await?;
+
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), news.id), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let tags = || {
+        store
+            .tags
+            .read()
+            .unwrap()
+            .iter()
+            .map(|t| (t.name.clone(), t.unread))
+            .collect::<Vec<_>>()
+    };
+    // categories are not imported by default
+    assert!(tags().is_empty());
+
+    // stored articles are tagged once it is enabled
+    let mut feed = fixture.feeds(news.id).remove(0);
+    feed.import_categories = true;
+    store
+        .send(|reply| Command::UpdateFeed((&feed).into(), reply))
+        .recv()
+        .await?;
+    assert!(fixture.feeds(news.id)[0].import_categories);
+    assert_eq!(tags(), [("Rust".to_owned(), 1)]);
+
+    let timeline =
+        |timeline, after| store.send(move |reply| Command::Timeline(timeline, after, 50, reply));
+    let article = |id| store.send(move |reply| Command::Article(id, reply)).recv();
+    let hello = timeline(Timeline::All, None).recv().await?.remove(0);
+    assert_eq!(article(hello.id).await?.tags, ["Rust"]);
+    // existing tags keep their case
+    let stored = store
+        .send(|reply| {
+            Command::SetTags(
+                hello.id,
+                vec!["rust".to_owned(), " Later ".to_owned(), "".to_owned()],
+                reply,
+            )
+        })
+        .recv()
+        .await?;
+    assert_eq!(stored, ["Rust", "Later"]);
+
+    // new articles are tagged with their categories
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(
+        tags(),
+        [
+            ("Later".to_owned(), 1),
+            ("News".to_owned(), 1),
+            ("Rust".to_owned(), 2)
+        ]
+    );
+    let rust = store.tags.read().unwrap()[2].id;
+    let tagged = timeline(Timeline::Tag(rust), None).recv().await?;
+    let titles = tagged.iter().map(|a| a.title.as_str()).collect::<Vec<_>>();
+    assert_eq!(titles, ["Again", "Hello"]);
+    assert_eq!(article(tagged[0].id).await?.tags.len(), 2);
+
+    let hits = store
+        .send(|reply| Command::Search("tag:later".to_owned(), reply))
+        .recv()
+        .await?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].article.title, "Hello");
+
+    store
+        .send(|reply| Command::MarkRead(Scope::Article(hello.id), None, reply))
+        .recv()
+        .await?;
+    assert_eq!(
+        tags(),
+        [
+            ("Later".to_owned(), 0),
+            ("News".to_owned(), 1),
+            ("Rust".to_owned(), 1)
+        ]
+    );
+
+    store
+        .send(|reply| Command::SetTags(hello.id, Vec::new(), reply))
+        .recv()
+        .await?;
+    assert_eq!(tags(), [("News".to_owned(), 1), ("Rust".to_owned(), 1)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn prune_old_read_articles() -> Result<()> {
+    let fixture = Fixture::new("prune")?;
+    let store = &fixture.store;
+    let body = RSS.replace(
+        "</channel>",
+        r#"<item>
+      <title>Again</title>
+      <link>https://example.com/again</link>
+      <pubDate>Tue, 02 Jan 2024 00:00:00 GMT</pubDate>
+    </item>
+  </channel>"#,
+    );
+    let xml = response("200 OK", "Content-Type: application/rss+xml\r\n", &body);
+    let (url, _) = serve(vec![xml.clone(), xml.clone(), xml]).await?;
+
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    let feed = store
+        .send(|reply| Command::CreateFeed(Feed::new(url, "Example".into(), news.id), reply))
+        .recv()
+        .await?;
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    let titles = || async {
+        store
+            .send(|reply| Command::Timeline(Timeline::All, None, 50, reply))
+            .recv()
+            .await
+            .map(|articles| {
+                articles
+                    .into_iter()
+                    .map(|a| (a.id, a.title))
+                    .collect::<Vec<_>>()
+            })
+    };
+    let prune = || store.send(Command::Prune);
+
+    // everything is kept by default
+    assert_eq!(
+        store.send(Command::Retention).recv().await?,
+        Retention::default()
+    );
+    assert_eq!(prune().recv().await?.articles, 0);
+
+    // published in 2024
+    store
+        .send(|reply| {
+            Command::SetRetention(
+                Retention {
+                    keep_days: 1,
+                    keep_articles: 0,
+                },
+                reply,
+            )
+        })
+        .recv()
+        .await?;
+    // unread articles are kept
+    assert_eq!(prune().recv().await?.articles, 0);
+    store
+        .send(|reply| Command::MarkRead(Scope::Feed(feed.id), None, reply))
+        .recv()
+        .await?;
+
+    // the feed keeps its latest article whatever its age
+    let mut feed = fixture.feeds(news.id).remove(0);
+    feed.keep_days = Some(0);
+    feed.keep_articles = Some(1);
+    store
+        .send(|reply| Command::UpdateFeed((&feed).into(), reply))
+        .recv()
+        .await?;
+    assert_eq!(prune().recv().await?.articles, 1);
+    let kept = titles().await?;
+    assert_eq!(
+        kept.iter().map(|(_, t)| t.as_str()).collect::<Vec<_>>(),
+        ["Again"]
+    );
+
+    // pruned articles are not stored again
+    store
+        .send(|reply| Command::FetchFeed(feed.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(titles().await?, kept);
+
+    // starred articles are kept, the global default applies again
+    feed.keep_days = None;
+    feed.keep_articles = None;
+    store
+        .send(|reply| Command::UpdateFeed((&feed).into(), reply))
+        .recv()
+        .await?;
+    let again = kept[0].0;
+    store
+        .send(|reply| Command::Star(again, true, reply))
+        .recv()
+        .await?;
+    assert_eq!(prune().recv().await?.articles, 0);
+    store
+        .send(|reply| Command::Star(again, false, reply))
+        .recv()
+        .await?;
+    assert_eq!(prune().recv().await?.articles, 1);
+    assert!(titles().await?.is_empty());
+    // free pages can be given back
+    let auto_vacuum: u32 = fixture
+        .pool
+        .get()?
+        .query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+    assert_eq!(auto_vacuum, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn images_are_fetched_once_then_cached() -> Result<()> {
+    let fixture = Fixture::new("images")?;
+    let mut png = Vec::new();
+    image::RgbaImage::new(3, 2).write_to(
+        &mut std::io::Cursor::new(&mut png),
+        image::ImageOutputFormat::Png,
+    )?;
+    let mut ok = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        png.len()
+    )
+    .into_bytes();
+    ok.extend(png);
+    let (url, served) = serve(vec![ok]).await?;
+
+    for _ in 0..2 {
+        let image = fixture
+            .store
+            .send(|reply| Command::Image(url.clone(), reply))
+            .recv()
+            .await?;
+        assert_eq!(image.dimensions(), (3, 2));
+    }
+    assert_eq!(served.load(Ordering::SeqCst), 1);
+
+    let (url, _) = serve(vec![
+        "HTTP/1.1 200 OK\r\nContent-Length: 104857600\r\nConnection: close\r\n\r\n".to_owned(),
+        response("200 OK", "Content-Type: text/html\r\n", "<html></html>"),
+    ])
+    .await?;
+    let result = fixture
+        .store
+        .send(|reply| Command::Image(url.clone(), reply))
+        .recv()
+        .await;
+    assert!(matches!(result, Err(ImageError::TooLarge(104857600))));
+    let result = fixture
+        .store
+        .send(|reply| Command::Image(url.clone(), reply))
+        .recv()
+        .await;
+    assert!(matches!(result, Err(ImageError::Decode(_))));
+    // only images which decode are kept
+    assert_eq!(fs::read_dir(fixture.dir.join("images"))?.count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn refresh_more_feeds_than_pooled_connections() -> Result<()> {
+    // the pool keeps 10 connections
+    const FEEDS: usize = 12;
+    let fixture = Fixture::new("refresh-many")?;
+    let store = &fixture.store;
+    let (url, served) = serve(vec![rss(); FEEDS * 3]).await?;
+
+    // seeded feeds are not fetched from the network
+    let seeded = store.folders.read().unwrap().clone();
+    for feed in seeded.into_iter().filter_map(|f| f.feeds).flatten() {
+        store
+            .send(|reply| Command::DeleteFeed(feed, reply))
+            .recv()
+            .await?;
+    }
+    let news = store
+        .send(|reply| Command::CreateFolder(folder("News"), reply))
+        .recv()
+        .await?;
+    for i in 0..FEEDS {
+        store
+            .send(|reply| {
+                Command::CreateFeed(
+                    Feed::new(format!("{url}?{i}"), format!("{i}"), news.id),
+                    reply,
+                )
+            })
+            .recv()
+            .await?;
+    }
+    let settled = || async {
+        for _ in 0..200 {
+            let feeds = fixture.feeds(news.id);
+            if feeds.iter().all(|f| !f.state.is_busy()) {
+                return feeds;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        panic!("fetches are still running");
+    };
+
+    let queued = store.send(Command::FetchAll).recv().await?;
+    assert_eq!(queued, FEEDS);
+    let feeds = settled().await;
+    assert!(feeds
+        .iter()
+        .all(|f| f.state == FetchState::Idle && f.last_fetched.is_some()));
+    assert_eq!(served.load(Ordering::SeqCst), FEEDS * 2);
+
+    let queued = store
+        .send(|reply| Command::FetchFolder(news.clone(), reply))
+        .recv()
+        .await?;
+    assert_eq!(queued, FEEDS);
+    let feeds = settled().await;
+    assert!(feeds.iter().all(|f| f.last_error.is_none()));
+    assert_eq!(served.load(Ordering::SeqCst), FEEDS * 3);
+    Ok(())
+}